@@ -0,0 +1,79 @@
+use nalgebra::Point3;
+use procedural_generation::image_export::{Image, Plane, Slice};
+use procedural_generation::noise::permutation_table::PermutationTable;
+use procedural_generation::resources::rarity::RarityLayers;
+use std::env;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "Usage: noise_image <simplex|curl|voronoi|rarity> <output.pgm|ppm|png> \
+[width] [height] [pixel_size] [x] [y] [z] [time] [seed] [xy|xz|yz]";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.len() < 2 {
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+
+    // Optional numbers after the noise and output, with defaults
+    let number = |index: usize, default: f64| -> f64 {
+        match args.get(index) {
+            Some(arg) => arg.parse().unwrap_or_else(|_| {
+                eprintln!("Invalid number: {}\n{}", arg, USAGE);
+                process::exit(1);
+            }),
+            None => default,
+        }
+    };
+
+    let plane = match args.get(10).map(String::as_str) {
+        None | Some("xy") => Plane::Xy,
+        Some("xz") => Plane::Xz,
+        Some("yz") => Plane::Yz,
+        Some(other) => {
+            eprintln!("Unknown plane: {}\n{}", other, USAGE);
+            process::exit(1);
+        }
+    };
+
+    let slice = Slice {
+        plane,
+        width: number(2, 512.0) as u32,
+        height: number(3, 512.0) as u32,
+        pixel_size: number(4, 0.02),
+        origin: Point3::new(number(5, 0.0), number(6, 0.0), number(7, 0.0)),
+        time: number(8, 0.0),
+    };
+
+    let seed = number(9, 0.0) as u64;
+
+    let image = match args[0].as_str() {
+        "simplex" => Image::simplex(&slice, &PermutationTable::from_seed(seed)),
+        "curl" => {
+            let tables = [
+                PermutationTable::from_seed(seed),
+                PermutationTable::from_seed(seed.wrapping_add(1)),
+                PermutationTable::from_seed(seed.wrapping_add(2)),
+            ];
+
+            Image::curl(&slice, &tables)
+        }
+        "voronoi" => Image::smooth_voronoi(&slice, 1.0, 32.0, seed),
+        "rarity" => Image::rarity(
+            &slice,
+            &RarityLayers::default(),
+            &PermutationTable::from_seed(seed),
+        ),
+        other => {
+            eprintln!("Unknown noise: {}\n{}", other, USAGE);
+            process::exit(1);
+        }
+    };
+
+    if let Err(error) = image.and_then(|image| image.save(Path::new(&args[1]))) {
+        eprintln!("Export failed: {:?}", error);
+        process::exit(1);
+    }
+}
@@ -35,17 +35,13 @@ pub struct User {
     id: DatabaseId,
 }
 
-#[derive(PartialEq, Clone, Copy, Eq, Hash)]
+#[derive(PartialEq, Clone, Copy, Eq, Hash, Default)]
 ///20 alphanumeric characters uniquely identifying a document in Firestore
 pub struct DatabaseId {
     data: [u8; 20],
 }
 
 impl DatabaseId {
-    pub fn default() -> Self {
-        Self { data: [0u8; 20] }
-    }
-
     pub fn from_string(seed: &str) -> Result<Self, &'static str> {
         if seed.len() > 20 {
             return Err("Length must be 20");
@@ -62,4 +58,8 @@ impl DatabaseId {
 
         Ok(Self { data: array })
     }
+
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.data
+    }
 }
@@ -0,0 +1,365 @@
+#![allow(dead_code)]
+
+use crate::noise::curl;
+use crate::noise::permutation_table::PermutationTable;
+use crate::noise::simplex;
+use crate::noise::smooth_voronoi;
+use crate::resources::rarity::RarityLayers;
+use nalgebra::{Point3, Point4, Vector3};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/*
+    Render slices of noise to images for visual debugging.
+    The slice is an axis aligned plane through origin, one pixel is pixel_size units wide.
+*/
+
+#[derive(Debug, PartialEq)]
+pub enum ExportError {
+    Io(String),
+    Png(String),
+    /// Only .pgm, .ppm and .png are supported
+    UnknownFormat,
+    /// The pixels don't fit in memory
+    TooLarge,
+}
+
+impl From<std::io::Error> for ExportError {
+    fn from(error: std::io::Error) -> Self {
+        ExportError::Io(error.to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+    Pgm,
+    Ppm,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &Path) -> Result<Self, ExportError> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("pgm") => Ok(ImageFormat::Pgm),
+            Some("ppm") => Ok(ImageFormat::Ppm),
+            Some("png") => Ok(ImageFormat::Png),
+            _ => Err(ExportError::UnknownFormat),
+        }
+    }
+}
+
+/// Axes along the image width and height.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Plane {
+    Xy,
+    /// Horizontal, the plane of the asteroid fields
+    Xz,
+    Yz,
+}
+
+impl Plane {
+    pub fn axes(&self) -> (Vector3<f64>, Vector3<f64>) {
+        match self {
+            Plane::Xy => (Vector3::x(), Vector3::y()),
+            Plane::Xz => (Vector3::x(), Vector3::z()),
+            Plane::Yz => (Vector3::y(), Vector3::z()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Slice {
+    pub origin: Point3<f64>,
+    pub plane: Plane,
+    pub width: u32,
+    pub height: u32,
+    pub pixel_size: f64,
+    pub time: f64,
+}
+
+impl Slice {
+    /// Position at the corner of a pixel, the second axis grows downward like the image.
+    pub fn position(&self, x: u32, y: u32) -> Point3<f64> {
+        let (horizontal, vertical) = self.plane.axes();
+
+        self.origin + (horizontal * x as f64 + vertical * y as f64) * self.pixel_size
+    }
+}
+
+/// 8 bit grayscale or RGB pixels, row by row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub rgb: bool,
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    /// Values from min to max are mapped to black to white.
+    pub fn gray<F>(slice: &Slice, min: f64, max: f64, mut sample: F) -> Result<Self, ExportError>
+    where
+        F: FnMut(&Point3<f64>) -> f64,
+    {
+        let mut data = Vec::with_capacity(byte_count(slice, 1)?);
+
+        for y in 0..slice.height {
+            for x in 0..slice.width {
+                let value = sample(&slice.position(x, y));
+
+                data.push(to_byte((value - min) / (max - min)));
+            }
+        }
+
+        Ok(Self {
+            width: slice.width,
+            height: slice.height,
+            rgb: false,
+            data,
+        })
+    }
+
+    /// Each channel from 0 to 1 is mapped to 0 to 255.
+    pub fn color<F>(slice: &Slice, mut sample: F) -> Result<Self, ExportError>
+    where
+        F: FnMut(&Point3<f64>) -> [f64; 3],
+    {
+        let mut data = Vec::with_capacity(byte_count(slice, 3)?);
+
+        for y in 0..slice.height {
+            for x in 0..slice.width {
+                data.extend(sample(&slice.position(x, y)).iter().map(|c| to_byte(*c)));
+            }
+        }
+
+        Ok(Self {
+            width: slice.width,
+            height: slice.height,
+            rgb: true,
+            data,
+        })
+    }
+
+    /// 4D simplex noise at the slice time.
+    pub fn simplex(slice: &Slice, table: &PermutationTable) -> Result<Self, ExportError> {
+        Self::gray(slice, -1.0, 1.0, |position| {
+            let space_time = Point4::new(position.x, position.y, position.z, slice.time);
+
+            simplex::with_derivatives_4d(&space_time, table).0
+        })
+    }
+
+    /// Direction of the flow as colours, each axis -1 to 1 mapped to a channel.
+    pub fn curl(slice: &Slice, tables: &[PermutationTable; 3]) -> Result<Self, ExportError> {
+        Self::color(slice, |position| {
+            let flow = curl::curl_noise_3d(position, slice.time, tables);
+
+            let direction = flow.try_normalize(1e-12).unwrap_or_else(Vector3::zeros);
+
+            [
+                direction.x * 0.5 + 0.5,
+                direction.y * 0.5 + 0.5,
+                direction.z * 0.5 + 0.5,
+            ]
+        })
+    }
+
+    /// Distance of zero is black, one cell width is white.
+    pub fn smooth_voronoi(
+        slice: &Slice,
+        scale: f64,
+        hardness: f64,
+        seed: u64,
+    ) -> Result<Self, ExportError> {
+        Self::gray(slice, 0.0, 1.0, |position| {
+            smooth_voronoi::smooth_voronoi_3d(*position, scale, hardness, seed).0
+        })
+    }
+
+    /// Rarity from -1 to 1 at the slice time, truncated to whole time steps.
+    pub fn rarity(
+        slice: &Slice,
+        layers: &RarityLayers,
+        table: &PermutationTable,
+    ) -> Result<Self, ExportError> {
+        let time = slice.time as u64;
+
+        Self::gray(slice, -1.0, 1.0, |position| {
+            layers.sample(position, time, table)
+        })
+    }
+
+    /// Format chosen from the file extension.
+    pub fn save(&self, path: &Path) -> Result<(), ExportError> {
+        let format = ImageFormat::from_path(path)?;
+
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        self.write(&mut writer, format)?;
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W, format: ImageFormat) -> Result<(), ExportError> {
+        match format {
+            ImageFormat::Pgm => {
+                write!(writer, "P5\n{} {}\n255\n", self.width, self.height)?;
+                writer.write_all(&self.to_gray())?;
+            }
+            ImageFormat::Ppm => {
+                write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+                writer.write_all(&self.to_rgb())?;
+            }
+            ImageFormat::Png => {
+                let mut encoder = png::Encoder::new(writer, self.width, self.height);
+
+                encoder.set_color(if self.rgb {
+                    png::ColorType::Rgb
+                } else {
+                    png::ColorType::Grayscale
+                });
+                encoder.set_depth(png::BitDepth::Eight);
+
+                encoder
+                    .write_header()
+                    .and_then(|mut png_writer| png_writer.write_image_data(&self.data))
+                    .map_err(|e| ExportError::Png(e.to_string()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn to_gray(&self) -> Vec<u8> {
+        if !self.rgb {
+            return self.data.clone();
+        }
+
+        self.data
+            .chunks(3)
+            .map(|pixel| ((pixel[0] as u32 + pixel[1] as u32 + pixel[2] as u32) / 3) as u8)
+            .collect()
+    }
+
+    fn to_rgb(&self) -> Vec<u8> {
+        if self.rgb {
+            return self.data.clone();
+        }
+
+        self.data.iter().flat_map(|value| vec![*value; 3]).collect()
+    }
+}
+
+/// Size of the pixel data, computed in usize so large slices can't wrap around.
+fn byte_count(slice: &Slice, channels: usize) -> Result<usize, ExportError> {
+    (slice.width as usize)
+        .checked_mul(slice.height as usize)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .ok_or(ExportError::TooLarge)
+}
+
+fn to_byte(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode() {
+        let slice = Slice {
+            origin: Point3::new(-4.0, 2.0, 0.5),
+            plane: Plane::Xy,
+            width: 16,
+            height: 8,
+            pixel_size: 0.25,
+            time: 1.0,
+        };
+
+        let image = Image::simplex(&slice, &PermutationTable::from_seed(1)).unwrap();
+
+        let mut pgm = Vec::new();
+        image.write(&mut pgm, ImageFormat::Pgm).unwrap();
+
+        assert!(pgm.starts_with(b"P5\n16 8\n255\n"));
+        assert_eq!(pgm.len(), 12 + 16 * 8);
+
+        let mut ppm = Vec::new();
+        image.write(&mut ppm, ImageFormat::Ppm).unwrap();
+
+        assert_eq!(ppm.len(), 12 + 16 * 8 * 3);
+
+        let mut encoded = Vec::new();
+        image.write(&mut encoded, ImageFormat::Png).unwrap();
+
+        let mut reader = png::Decoder::new(encoded.as_slice()).read_info().unwrap();
+        let mut decoded = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut decoded).unwrap();
+
+        assert_eq!(decoded, image.data);
+
+        let rarity = Image::rarity(
+            &slice,
+            &RarityLayers::default(),
+            &PermutationTable::from_seed(1),
+        )
+        .unwrap();
+
+        assert_eq!(rarity.data.len(), 16 * 8);
+        assert!(!rarity.rgb);
+
+        assert_eq!(
+            ImageFormat::from_path(Path::new("noise.jpg")),
+            Err(ExportError::UnknownFormat)
+        );
+    }
+
+    #[test]
+    fn planes() {
+        let mut slice = Slice {
+            origin: Point3::new(1.0, 2.0, 3.0),
+            plane: Plane::Xz,
+            width: 4,
+            height: 4,
+            pixel_size: 0.5,
+            time: 0.0,
+        };
+
+        assert_eq!(slice.position(2, 1), Point3::new(2.0, 2.0, 3.5));
+
+        slice.plane = Plane::Yz;
+
+        assert_eq!(slice.position(2, 1), Point3::new(1.0, 3.0, 3.5));
+    }
+
+    #[test]
+    fn byte_overflow() {
+        let slice = Slice {
+            origin: Point3::origin(),
+            plane: Plane::Xy,
+            width: 640,
+            height: 480,
+            pixel_size: 1.0,
+            time: 0.0,
+        };
+
+        assert_eq!(byte_count(&slice, 3), Ok(640 * 480 * 3));
+
+        // Used to wrap around in u32 and allocate too little
+        let huge = Slice {
+            width: u32::MAX,
+            height: u32::MAX,
+            ..slice
+        };
+
+        assert_eq!(byte_count(&huge, 3), Err(ExportError::TooLarge));
+        assert_eq!(
+            Image::color(&huge, |_| [0.0; 3]),
+            Err(ExportError::TooLarge)
+        );
+    }
+}
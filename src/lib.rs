@@ -1,5 +1,6 @@
 pub mod id_types;
+pub mod image_export;
 pub mod modules;
-mod noise;
+pub mod noise;
 pub mod resources;
 pub mod world;
@@ -0,0 +1,399 @@
+#![allow(dead_code)]
+
+use crate::noise::permutation_table::PermutationTable;
+use crate::noise::simplex::{corners_3d, corners_4d, falloff_3d, falloff_4d, skew_3d, skew_4d};
+use crate::noise::simplex::{NORMALIZE_3D, NORMALIZE_4D};
+use nalgebra::{Point3, Point4};
+use nalgebra::{Vector3, Vector4};
+
+/*
+    Simplex noise over many points at once.
+    Points are processed in chunks, one pass per step of the algorithm.
+    Skewing and falloff passes are plain arithmetic over arrays that the compiler can vectorize.
+    Only the corner ordering and gradient lookups are done point by point.
+    Results are identical to the scalar functions since they share the same code.
+*/
+
+const CHUNK: usize = 64;
+
+/// Same as simplex::with_derivatives_3d for every point.
+pub fn with_derivatives_3d(
+    points: &[Point3<f64>],
+    table: &PermutationTable,
+    values: &mut [f64],
+    derivatives: &mut [Vector3<f64>],
+) {
+    assert_eq!(points.len(), values.len());
+    assert_eq!(points.len(), derivatives.len());
+
+    for ((points, values), derivatives) in points
+        .chunks(CHUNK)
+        .zip(values.chunks_mut(CHUNK))
+        .zip(derivatives.chunks_mut(CHUNK))
+    {
+        let mut chunk = Chunk3::default();
+
+        for (index, point) in points.iter().enumerate() {
+            chunk.x[index] = point.x;
+            chunk.y[index] = point.y;
+            chunk.z[index] = point.z;
+        }
+
+        chunk.evaluate(points.len(), table);
+        chunk.write(values, derivatives);
+    }
+}
+
+/// Sample a regular grid starting at origin, x varies fastest then y then z.
+/// Point (x, y, z) is at origin + step * (x, y, z) per axis.
+pub fn grid_3d(
+    origin: &Point3<f64>,
+    step: &Vector3<f64>,
+    size: [usize; 3],
+    table: &PermutationTable,
+    values: &mut [f64],
+    derivatives: &mut [Vector3<f64>],
+) {
+    let count = size[0] * size[1] * size[2];
+
+    assert_eq!(count, values.len());
+    assert_eq!(count, derivatives.len());
+
+    for (chunk_index, (values, derivatives)) in values
+        .chunks_mut(CHUNK)
+        .zip(derivatives.chunks_mut(CHUNK))
+        .enumerate()
+    {
+        let mut chunk = Chunk3::default();
+
+        for index in 0..values.len() {
+            let grid_index = chunk_index * CHUNK + index;
+
+            let x = grid_index % size[0];
+            let y = (grid_index / size[0]) % size[1];
+            let z = grid_index / (size[0] * size[1]);
+
+            chunk.x[index] = origin.x + step.x * x as f64;
+            chunk.y[index] = origin.y + step.y * y as f64;
+            chunk.z[index] = origin.z + step.z * z as f64;
+        }
+
+        chunk.evaluate(values.len(), table);
+        chunk.write(values, derivatives);
+    }
+}
+
+/// Same as simplex::with_derivatives_4d for every point.
+pub fn with_derivatives_4d(
+    points: &[Point4<f64>],
+    table: &PermutationTable,
+    values: &mut [f64],
+    derivatives: &mut [Vector4<f64>],
+) {
+    assert_eq!(points.len(), values.len());
+    assert_eq!(points.len(), derivatives.len());
+
+    for ((points, values), derivatives) in points
+        .chunks(CHUNK)
+        .zip(values.chunks_mut(CHUNK))
+        .zip(derivatives.chunks_mut(CHUNK))
+    {
+        let mut chunk = Chunk4::default();
+
+        for (index, point) in points.iter().enumerate() {
+            chunk.x[index] = point.x;
+            chunk.y[index] = point.y;
+            chunk.z[index] = point.z;
+            chunk.w[index] = point.w;
+        }
+
+        chunk.evaluate(points.len(), table);
+        chunk.write(values, derivatives);
+    }
+}
+
+/// Sample a regular grid starting at origin, x varies fastest then y, z and w.
+/// Point (x, y, z, w) is at origin + step * (x, y, z, w) per axis.
+pub fn grid_4d(
+    origin: &Point4<f64>,
+    step: &Vector4<f64>,
+    size: [usize; 4],
+    table: &PermutationTable,
+    values: &mut [f64],
+    derivatives: &mut [Vector4<f64>],
+) {
+    let count = size[0] * size[1] * size[2] * size[3];
+
+    assert_eq!(count, values.len());
+    assert_eq!(count, derivatives.len());
+
+    for (chunk_index, (values, derivatives)) in values
+        .chunks_mut(CHUNK)
+        .zip(derivatives.chunks_mut(CHUNK))
+        .enumerate()
+    {
+        let mut chunk = Chunk4::default();
+
+        for index in 0..values.len() {
+            let grid_index = chunk_index * CHUNK + index;
+
+            let x = grid_index % size[0];
+            let y = (grid_index / size[0]) % size[1];
+            let z = (grid_index / (size[0] * size[1])) % size[2];
+            let w = grid_index / (size[0] * size[1] * size[2]);
+
+            chunk.x[index] = origin.x + step.x * x as f64;
+            chunk.y[index] = origin.y + step.y * y as f64;
+            chunk.z[index] = origin.z + step.z * z as f64;
+            chunk.w[index] = origin.w + step.w * w as f64;
+        }
+
+        chunk.evaluate(values.len(), table);
+        chunk.write(values, derivatives);
+    }
+}
+
+/// Structure of arrays, one array per component.
+struct Chunk3 {
+    x: [f64; CHUNK],
+    y: [f64; CHUNK],
+    z: [f64; CHUNK],
+    cells: [[f64; 3]; CHUNK],
+    offsets: [[[f64; 3]; CHUNK]; 4],
+    gradients: [[[f64; 3]; CHUNK]; 4],
+    values: [f64; CHUNK],
+    derivatives: [[f64; 3]; CHUNK],
+}
+
+impl Default for Chunk3 {
+    fn default() -> Self {
+        Self {
+            x: [0.0; CHUNK],
+            y: [0.0; CHUNK],
+            z: [0.0; CHUNK],
+            cells: [[0.0; 3]; CHUNK],
+            offsets: [[[0.0; 3]; CHUNK]; 4],
+            gradients: [[[0.0; 3]; CHUNK]; 4],
+            values: [0.0; CHUNK],
+            derivatives: [[0.0; 3]; CHUNK],
+        }
+    }
+}
+
+impl Chunk3 {
+    fn evaluate(&mut self, len: usize, table: &PermutationTable) {
+        // Skewing pass
+        for index in 0..len {
+            let (cell, offset) = skew_3d(self.x[index], self.y[index], self.z[index]);
+
+            self.cells[index] = cell;
+            // First corner is the cell origin
+            self.offsets[0][index] = offset;
+        }
+
+        // Corners and gradients pass
+        for index in 0..len {
+            let cell = self.cells[index];
+            let cell = [cell[0] as i64, cell[1] as i64, cell[2] as i64];
+
+            let (offsets, gradients) = corners_3d(cell, self.offsets[0][index], table);
+
+            for corner in 0..4 {
+                self.offsets[corner][index] = offsets[corner];
+                self.gradients[corner][index] = gradients[corner];
+            }
+        }
+
+        // Falloff pass, in the same corner order as the scalar function
+        for corner in 0..4 {
+            for index in 0..len {
+                let (value, deriv) =
+                    falloff_3d(&self.offsets[corner][index], &self.gradients[corner][index]);
+
+                self.values[index] += value;
+                self.derivatives[index][0] += deriv[0];
+                self.derivatives[index][1] += deriv[1];
+                self.derivatives[index][2] += deriv[2];
+            }
+        }
+    }
+
+    fn write(&self, values: &mut [f64], derivatives: &mut [Vector3<f64>]) {
+        for (index, (value, deriv)) in values.iter_mut().zip(derivatives.iter_mut()).enumerate() {
+            *value = self.values[index] * NORMALIZE_3D;
+            *deriv = Vector3::from(self.derivatives[index]) * NORMALIZE_3D;
+        }
+    }
+}
+
+/// Structure of arrays, one array per component.
+struct Chunk4 {
+    x: [f64; CHUNK],
+    y: [f64; CHUNK],
+    z: [f64; CHUNK],
+    w: [f64; CHUNK],
+    cells: [[f64; 4]; CHUNK],
+    offsets: [[[f64; 4]; CHUNK]; 5],
+    gradients: [[[f64; 4]; CHUNK]; 5],
+    values: [f64; CHUNK],
+    derivatives: [[f64; 4]; CHUNK],
+}
+
+impl Default for Chunk4 {
+    fn default() -> Self {
+        Self {
+            x: [0.0; CHUNK],
+            y: [0.0; CHUNK],
+            z: [0.0; CHUNK],
+            w: [0.0; CHUNK],
+            cells: [[0.0; 4]; CHUNK],
+            offsets: [[[0.0; 4]; CHUNK]; 5],
+            gradients: [[[0.0; 4]; CHUNK]; 5],
+            values: [0.0; CHUNK],
+            derivatives: [[0.0; 4]; CHUNK],
+        }
+    }
+}
+
+impl Chunk4 {
+    fn evaluate(&mut self, len: usize, table: &PermutationTable) {
+        // Skewing pass
+        for index in 0..len {
+            let (cell, offset) =
+                skew_4d(self.x[index], self.y[index], self.z[index], self.w[index]);
+
+            self.cells[index] = cell;
+            // First corner is the cell origin
+            self.offsets[0][index] = offset;
+        }
+
+        // Corners and gradients pass
+        for index in 0..len {
+            let cell = self.cells[index];
+            let cell = [
+                cell[0] as i64,
+                cell[1] as i64,
+                cell[2] as i64,
+                cell[3] as i64,
+            ];
+
+            let (offsets, gradients) = corners_4d(cell, self.offsets[0][index], table);
+
+            for corner in 0..5 {
+                self.offsets[corner][index] = offsets[corner];
+                self.gradients[corner][index] = gradients[corner];
+            }
+        }
+
+        // Falloff pass, in the same corner order as the scalar function
+        for corner in 0..5 {
+            for index in 0..len {
+                let (value, deriv) =
+                    falloff_4d(&self.offsets[corner][index], &self.gradients[corner][index]);
+
+                self.values[index] += value;
+                self.derivatives[index][0] += deriv[0];
+                self.derivatives[index][1] += deriv[1];
+                self.derivatives[index][2] += deriv[2];
+                self.derivatives[index][3] += deriv[3];
+            }
+        }
+    }
+
+    fn write(&self, values: &mut [f64], derivatives: &mut [Vector4<f64>]) {
+        for (index, (value, deriv)) in values.iter_mut().zip(derivatives.iter_mut()).enumerate() {
+            *value = self.values[index] * NORMALIZE_4D;
+            *deriv = Vector4::from(self.derivatives[index]) * NORMALIZE_4D;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::simplex;
+
+    #[test]
+    fn same_as_scalar_3d() {
+        let table = PermutationTable::from_seed(1);
+
+        let origin = Point3::new(-13.7, 2.1, 0.4);
+        let step = Vector3::new(0.31, 0.17, 0.53);
+        let size = [7, 9, 5];
+
+        let count = size[0] * size[1] * size[2];
+
+        let mut values = vec![0.0; count];
+        let mut derivatives = vec![Vector3::zeros(); count];
+
+        grid_3d(&origin, &step, size, &table, &mut values, &mut derivatives);
+
+        let mut points = Vec::with_capacity(count);
+
+        for z in 0..size[2] {
+            for y in 0..size[1] {
+                for x in 0..size[0] {
+                    points.push(Point3::new(
+                        origin.x + step.x * x as f64,
+                        origin.y + step.y * y as f64,
+                        origin.z + step.z * z as f64,
+                    ));
+                }
+            }
+        }
+
+        let mut batch_values = vec![0.0; count];
+        let mut batch_derivatives = vec![Vector3::zeros(); count];
+
+        with_derivatives_3d(&points, &table, &mut batch_values, &mut batch_derivatives);
+
+        for (index, point) in points.iter().enumerate() {
+            let (value, deriv) = simplex::with_derivatives_3d(point, &table);
+
+            assert_eq!(value, values[index]);
+            assert_eq!(deriv, derivatives[index]);
+            assert_eq!(value, batch_values[index]);
+            assert_eq!(deriv, batch_derivatives[index]);
+        }
+    }
+
+    #[test]
+    fn same_as_scalar_4d() {
+        let table = PermutationTable::from_seed(2);
+
+        let origin = Point4::new(-13.7, 2.1, 0.4, 100.0);
+        let step = Vector4::new(0.31, 0.17, 0.53, 0.05);
+        let size = [5, 4, 3, 6];
+
+        let count = size[0] * size[1] * size[2] * size[3];
+
+        let mut values = vec![0.0; count];
+        let mut derivatives = vec![Vector4::zeros(); count];
+
+        grid_4d(&origin, &step, size, &table, &mut values, &mut derivatives);
+
+        let mut index = 0;
+
+        for w in 0..size[3] {
+            for z in 0..size[2] {
+                for y in 0..size[1] {
+                    for x in 0..size[0] {
+                        let point = Point4::new(
+                            origin.x + step.x * x as f64,
+                            origin.y + step.y * y as f64,
+                            origin.z + step.z * z as f64,
+                            origin.w + step.w * w as f64,
+                        );
+
+                        let (value, deriv) = simplex::with_derivatives_4d(&point, &table);
+
+                        assert_eq!(value, values[index]);
+                        assert_eq!(deriv, derivatives[index]);
+
+                        index += 1;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,142 @@
+#![allow(dead_code)]
+
+use crate::noise::hash_fonctions;
+use nalgebra::{Point3, Vector2, Vector3};
+
+//https://thebookofshaders.com/12/
+
+/// Closest feature points of a cellular noise sample.
+/// Distances are in scaled space, one cell is one unit wide.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellSample {
+    /// Distance to the nearest feature point.
+    pub f1: f64,
+    /// Distance to the second nearest feature point.
+    pub f2: f64,
+    /// Nearest feature point, in the same space as the input coordinates.
+    pub feature: Point3<f64>,
+    /// Same for every point closest to this feature, for any position and scale.
+    pub id: u64,
+}
+
+/// Worley noise, one feature point per cell.
+pub fn cellular_3d(coordinates: Point3<f64>, scale: f64, seed: u64) -> CellSample {
+    let coordinates = coordinates * scale;
+
+    let integral = Point3::new(
+        coordinates.x.floor(),
+        coordinates.y.floor(),
+        coordinates.z.floor(),
+    );
+
+    let fract = coordinates - integral;
+
+    let cell = [integral.x as i64, integral.y as i64, integral.z as i64];
+
+    let mut f1 = f64::INFINITY;
+    let mut f2 = f64::INFINITY;
+    let mut nearest = [0; 3];
+    let mut nearest_offset = Vector3::zeros();
+
+    // Points can be up to 2 cells away when the closest ones are in the far corners.
+    for z in -2..3 {
+        for y in -2..3 {
+            for x in -2..3 {
+                let (i, j, k) = (cell[0] + x, cell[1] + y, cell[2] + z);
+
+                let offset =
+                    feature_point_3d(i, j, k, seed) + Vector3::new(x as f64, y as f64, z as f64);
+
+                let distance = (offset - fract).norm();
+
+                if distance < f1 {
+                    f2 = f1;
+                    f1 = distance;
+                    nearest = [i, j, k];
+                    nearest_offset = offset;
+                } else if distance < f2 {
+                    f2 = distance;
+                }
+            }
+        }
+    }
+
+    CellSample {
+        f1,
+        f2,
+        feature: (integral + nearest_offset) / scale,
+        id: hash_fonctions::hash_3d(nearest[0], nearest[1], nearest[2], seed ^ CELL_ID_SALT),
+    }
+}
+
+/// Position of the feature point inside its cell, each axis in [0, 1).
+pub(crate) fn feature_point_3d(i: i64, j: i64, k: i64, seed: u64) -> Vector3<f64> {
+    // One hash split in 21 bits per axis, offsetting the seed per axis would repeat the
+    // axes of the neighbouring seeds.
+    let hash = hash_fonctions::hash_3d(i, j, k, seed);
+
+    Vector3::new(
+        (hash >> 43) as f64,
+        ((hash >> 22) & MASK_21) as f64,
+        ((hash >> 1) & MASK_21) as f64,
+    ) * (1.0 / (1u64 << 21) as f64)
+}
+
+/// Position of the feature point inside its cell, each axis in [0, 1).
+pub(crate) fn feature_point_2d(i: i64, j: i64, seed: u64) -> Vector2<f64> {
+    // One hash split in 32 bits per axis
+    let hash = hash_fonctions::hash_2d(i, j, seed);
+
+    Vector2::new((hash >> 32) as f64, (hash as u32) as f64) * (1.0 / (1u64 << 32) as f64)
+}
+
+const MASK_21: u64 = (1 << 21) - 1;
+
+/// Cell IDs use their own hash stream, unrelated to the feature points of any nearby seed.
+const CELL_ID_SALT: u64 = 0x5851_f42d_4c95_7f2d;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::test_utils::sample_positions;
+
+    #[test]
+    fn nearest_features() {
+        let scale = 0.25;
+
+        for position in sample_positions(200).map(|position: Point3<f64>| position * 10.0) {
+            let sample = cellular_3d(position, scale, 9);
+
+            assert!(sample.f1 <= sample.f2);
+            assert!((sample.f1 - (sample.feature - position).norm() * scale).abs() < 1e-9);
+
+            // Anywhere on the segment to its feature point is in the same cell.
+            let halfway = position + (sample.feature - position) * 0.5;
+
+            assert_eq!(cellular_3d(halfway, scale, 9).id, sample.id);
+        }
+    }
+
+    #[test]
+    fn independent_seeds() {
+        for index in 0..100 {
+            let (i, j, k) = (index, index * -3, 7);
+
+            let point = feature_point_3d(i, j, k, 20);
+            let next = feature_point_3d(i, j, k, 21);
+
+            assert!(point
+                .iter()
+                .chain(next.iter())
+                .all(|c| (0.0..1.0).contains(c)));
+
+            // Neighbouring seeds used to share axes
+            assert_ne!(point.y, next.x);
+            assert_ne!(point.z, next.y);
+
+            let flat = feature_point_2d(i, j, 20);
+
+            assert_ne!(flat.y, feature_point_2d(i, j, 21).x);
+        }
+    }
+}
@@ -0,0 +1,291 @@
+#![allow(dead_code)]
+
+use crate::noise::noise_fn::{Domain, Noise, NoiseFn};
+
+/// Sum of two noises.
+pub struct Add<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A, B> Noise for Add<A, B> {}
+
+impl<A, B> Add<A, B> {
+    pub fn new(a: A, b: B) -> Self {
+        Self { a, b }
+    }
+}
+
+impl<P: Domain, A: NoiseFn<P>, B: NoiseFn<P>> NoiseFn<P> for Add<A, B> {
+    fn sample(&self, point: &P) -> (f64, P::Gradient) {
+        let (value_a, deriv_a) = self.a.sample(point);
+        let (value_b, deriv_b) = self.b.sample(point);
+
+        (value_a + value_b, deriv_a + deriv_b)
+    }
+}
+
+/// Product of two noises.
+pub struct Multiply<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A, B> Noise for Multiply<A, B> {}
+
+impl<A, B> Multiply<A, B> {
+    pub fn new(a: A, b: B) -> Self {
+        Self { a, b }
+    }
+}
+
+impl<P: Domain, A: NoiseFn<P>, B: NoiseFn<P>> NoiseFn<P> for Multiply<A, B> {
+    fn sample(&self, point: &P) -> (f64, P::Gradient) {
+        let (value_a, deriv_a) = self.a.sample(point);
+        let (value_b, deriv_b) = self.b.sample(point);
+
+        // Product rule
+        (value_a * value_b, deriv_a * value_b + deriv_b * value_a)
+    }
+}
+
+/// Noise times scale plus bias.
+pub struct ScaleBias<N> {
+    source: N,
+    scale: f64,
+    bias: f64,
+}
+
+impl<N> Noise for ScaleBias<N> {}
+
+impl<N> ScaleBias<N> {
+    pub fn new(source: N, scale: f64, bias: f64) -> Self {
+        Self {
+            source,
+            scale,
+            bias,
+        }
+    }
+}
+
+impl<P: Domain, N: NoiseFn<P>> NoiseFn<P> for ScaleBias<N> {
+    fn sample(&self, point: &P) -> (f64, P::Gradient) {
+        let (value, deriv) = self.source.sample(point);
+
+        (value * self.scale + self.bias, deriv * self.scale)
+    }
+}
+
+/// Noise restricted to [min, max]. Derivatives are zero where clamped.
+pub struct Clamp<N> {
+    source: N,
+    min: f64,
+    max: f64,
+}
+
+impl<N> Noise for Clamp<N> {}
+
+impl<N> Clamp<N> {
+    pub fn new(source: N, min: f64, max: f64) -> Self {
+        Self { source, min, max }
+    }
+}
+
+impl<P: Domain, N: NoiseFn<P>> NoiseFn<P> for Clamp<N> {
+    fn sample(&self, point: &P) -> (f64, P::Gradient) {
+        let (value, deriv) = self.source.sample(point);
+
+        if value < self.min {
+            (self.min, P::zero_gradient())
+        } else if value > self.max {
+            (self.max, P::zero_gradient())
+        } else {
+            (value, deriv)
+        }
+    }
+}
+
+/// Pick a or b depending on the control noise being below or above the threshold.
+/// Inside the falloff around the threshold, both are smoothly mixed.
+pub struct Select<A, B, C> {
+    a: A,
+    b: B,
+    control: C,
+    threshold: f64,
+    falloff: f64,
+}
+
+impl<A, B, C> Noise for Select<A, B, C> {}
+
+impl<A, B, C> Select<A, B, C> {
+    pub fn new(a: A, b: B, control: C, threshold: f64, falloff: f64) -> Self {
+        Self {
+            a,
+            b,
+            control,
+            threshold,
+            falloff: falloff.abs(),
+        }
+    }
+}
+
+impl<P, A, B, C> NoiseFn<P> for Select<A, B, C>
+where
+    P: Domain,
+    A: NoiseFn<P>,
+    B: NoiseFn<P>,
+    C: NoiseFn<P>,
+{
+    fn sample(&self, point: &P) -> (f64, P::Gradient) {
+        let (control, control_deriv) = self.control.sample(point);
+
+        let lower = self.threshold - self.falloff;
+        let upper = self.threshold + self.falloff;
+
+        if control <= lower {
+            return self.a.sample(point);
+        }
+
+        if control >= upper {
+            return self.b.sample(point);
+        }
+
+        let (value_a, deriv_a) = self.a.sample(point);
+        let (value_b, deriv_b) = self.b.sample(point);
+
+        // Smoothstep between lower and upper
+        let x = (control - lower) / (upper - lower);
+        let weight = x * x * (3.0 - 2.0 * x);
+        let weight_deriv = control_deriv * (6.0 * x * (1.0 - x) / (upper - lower));
+
+        mix(value_a, deriv_a, value_b, deriv_b, weight, weight_deriv)
+    }
+}
+
+/// Linear mix of a and b, a where the control noise is -1 and b where it is 1.
+pub struct Blend<A, B, C> {
+    a: A,
+    b: B,
+    control: C,
+}
+
+impl<A, B, C> Noise for Blend<A, B, C> {}
+
+impl<A, B, C> Blend<A, B, C> {
+    pub fn new(a: A, b: B, control: C) -> Self {
+        Self { a, b, control }
+    }
+}
+
+impl<P, A, B, C> NoiseFn<P> for Blend<A, B, C>
+where
+    P: Domain,
+    A: NoiseFn<P>,
+    B: NoiseFn<P>,
+    C: NoiseFn<P>,
+{
+    fn sample(&self, point: &P) -> (f64, P::Gradient) {
+        let (control, control_deriv) = self.control.sample(point);
+        let (value_a, deriv_a) = self.a.sample(point);
+        let (value_b, deriv_b) = self.b.sample(point);
+
+        // Scale from [-1, 1] to [0, 1]
+        let weight = (control + 1.0) * 0.5;
+        let weight_deriv = control_deriv * 0.5;
+
+        mix(value_a, deriv_a, value_b, deriv_b, weight, weight_deriv)
+    }
+}
+
+fn mix<V: Copy + std::ops::Add<Output = V> + std::ops::Mul<f64, Output = V>>(
+    value_a: f64,
+    deriv_a: V,
+    value_b: f64,
+    deriv_b: V,
+    weight: f64,
+    weight_deriv: V,
+) -> (f64, V) {
+    (
+        value_a * (1.0 - weight) + value_b * weight,
+        deriv_a * (1.0 - weight) + deriv_b * weight + weight_deriv * (value_b - value_a),
+    )
+}
+
+/// Noise sampled at matrix times point plus translation.
+/// Use it to scale, rotate or move a noise.
+pub struct Transform<N, M, T> {
+    source: N,
+    matrix: M,
+    translation: T,
+}
+
+impl<N, M, T> Noise for Transform<N, M, T> {}
+
+impl<N, M, T> Transform<N, M, T> {
+    pub fn new(source: N, matrix: M, translation: T) -> Self {
+        Self {
+            source,
+            matrix,
+            translation,
+        }
+    }
+}
+
+impl<P, N, M, T> NoiseFn<P> for Transform<N, M, T>
+where
+    P: Domain<Matrix = M, Gradient = T>,
+    N: NoiseFn<P>,
+{
+    fn sample(&self, point: &P) -> (f64, P::Gradient) {
+        let transformed = point.transform(&self.matrix, &self.translation);
+
+        let (value, deriv) = self.source.sample(&transformed);
+
+        (value, P::pull_back(&self.matrix, &deriv))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::fractal::Fractal;
+    use crate::noise::noise_fn::{Constant, NoiseFnExt, Ridged, Simplex};
+    use crate::noise::permutation_table::PermutationTable;
+    use crate::noise::test_utils::check_derivatives;
+    use nalgebra::{Matrix3, Point3, Rotation3, Vector3};
+
+    #[test]
+    fn composed_derivatives() {
+        let rotation = Rotation3::from_euler_angles(0.3, -1.2, 0.7);
+        let matrix: Matrix3<f64> = rotation.matrix() * 2.5;
+
+        let noise = Simplex::new(PermutationTable::from_seed(1))
+            .multiply(Simplex::new(PermutationTable::from_seed(2)).scale_bias(0.5, 0.5))
+            .add(Constant(0.25))
+            .blend(
+                Ridged(Fractal::new(&[3, 4])),
+                Simplex::new(PermutationTable::from_seed(5)),
+            )
+            .select(
+                Simplex::new(PermutationTable::from_seed(6)).clamp(-0.5, 0.5),
+                Simplex::new(PermutationTable::from_seed(7)),
+                0.1,
+                0.3,
+            )
+            .transform(matrix, Vector3::new(10.0, -3.0, 0.5));
+
+        check_derivatives(1e-3, |position: &Point3<f64>| noise.sample(position));
+    }
+
+    #[test]
+    fn no_shadowing() {
+        // NoiseFnExt is in scope, the std methods of the same name must still resolve
+        assert_eq!(5i32.clamp(0, 3), 3);
+        assert_eq!(1.5f64.clamp(0.0, 1.0), 1.0);
+
+        let boxed: Box<dyn NoiseFn<Point3<f64>>> = Box::new(Constant(2.0));
+        let (value, _) = boxed.scale_bias(0.5, 1.0).sample(&Point3::origin());
+
+        assert_eq!(value, 2.0);
+    }
+}
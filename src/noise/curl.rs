@@ -1,20 +1,43 @@
 #![allow(dead_code)]
 
+use crate::noise::fractal::Fractal;
+use crate::noise::hash_fonctions;
+use crate::noise::permutation_table::PermutationTable;
 use crate::noise::simplex;
-use crate::noise::simplex::{SEED1, SEED2, SEED3};
-use nalgebra::base::{Vector2, Vector3, Vector6};
+use nalgebra::base::{Matrix3, Vector2, Vector3, Vector6};
 use nalgebra::geometry::{Point2, Point3, Point4};
 
 //https://www.cs.ubc.ca/~rbridson/docs/bridson-siggraph2007-curlnoise.pdf
 
-pub fn curl_noise_2d(coordinates: &Point2<f64>, time: f64) -> Vector2<f64> {
-    let space_time = Point3::new(coordinates.x, coordinates.y, time);
+pub fn curl_noise_2d(
+    coordinates: &Point2<f64>,
+    time: f64,
+    table: &PermutationTable,
+) -> Vector2<f64> {
+    let (_, derivatives) = simplex::with_derivatives_2d_over_time(coordinates, time, table);
 
-    let (_, deriv) = simplex::with_derivatives_3d(&space_time, &SEED1);
+    curl_2d(&derivatives)
+}
+
+/// Curl noise sliding around obstacles instead of crossing them.
+/// The distance field returns the distance to the closest surface and its gradient.
+/// The flow is unchanged further than the influence distance.
+pub fn curl_noise_2d_with_obstacles<F>(
+    coordinates: &Point2<f64>,
+    time: f64,
+    table: &PermutationTable,
+    distance_field: F,
+    influence: f64,
+) -> Vector2<f64>
+where
+    F: Fn(&Point2<f64>) -> (f64, Vector2<f64>),
+{
+    let (potential, derivatives) = simplex::with_derivatives_2d_over_time(coordinates, time, table);
 
-    let derivatives = &Vector2::new(deriv.x, deriv.y);
+    let (ramp, ramp_deriv) = obstacle_ramp(distance_field(coordinates), influence);
 
-    curl_2d(derivatives)
+    // Product rule of the ramped potential
+    curl_2d(&(derivatives * ramp + ramp_deriv * potential))
 }
 
 fn curl_2d(derivatives: &Vector2<f64>) -> Vector2<f64> {
@@ -23,12 +46,17 @@ fn curl_2d(derivatives: &Vector2<f64>) -> Vector2<f64> {
     Vector2::new(derivatives.y, -derivatives.x)
 }
 
-pub fn curl_noise_3d(coordinates: &Point3<f64>, time: f64) -> Vector3<f64> {
+/// Every potential field needs its own table.
+pub fn curl_noise_3d(
+    coordinates: &Point3<f64>,
+    time: f64,
+    tables: &[PermutationTable; 3],
+) -> Vector3<f64> {
     let space_time = Point4::new(coordinates.x, coordinates.y, coordinates.z, time);
 
-    let (_, deriv_1) = simplex::with_derivatives_4d(&space_time, &SEED1);
-    let (_, deriv_2) = simplex::with_derivatives_4d(&space_time, &SEED2);
-    let (_, deriv_3) = simplex::with_derivatives_4d(&space_time, &SEED3);
+    let (_, deriv_1) = simplex::with_derivatives_4d(&space_time, &tables[0]);
+    let (_, deriv_2) = simplex::with_derivatives_4d(&space_time, &tables[1]);
+    let (_, deriv_3) = simplex::with_derivatives_4d(&space_time, &tables[2]);
 
     let derivatives = &Vector6::new(
         deriv_1.y, deriv_1.z, deriv_2.x, deriv_2.z, deriv_3.x, deriv_3.y,
@@ -37,6 +65,89 @@ pub fn curl_noise_3d(coordinates: &Point3<f64>, time: f64) -> Vector3<f64> {
     curl_3d(derivatives)
 }
 
+/// Same as curl_noise_3d with the jacobian of the flow, row i is the gradient of the flow axis i.
+pub fn curl_noise_3d_with_jacobian(
+    coordinates: &Point3<f64>,
+    time: f64,
+    tables: &[PermutationTable; 3],
+) -> (Vector3<f64>, Matrix3<f64>) {
+    let space_time = Point4::new(coordinates.x, coordinates.y, coordinates.z, time);
+
+    let (_, deriv_1, hessian_1) = simplex::with_hessian_4d(&space_time, &tables[0]);
+    let (_, deriv_2, hessian_2) = simplex::with_hessian_4d(&space_time, &tables[1]);
+    let (_, deriv_3, hessian_3) = simplex::with_hessian_4d(&space_time, &tables[2]);
+
+    let flow = curl_3d(&Vector6::new(
+        deriv_1.y, deriv_1.z, deriv_2.x, deriv_2.z, deriv_3.x, deriv_3.y,
+    ));
+
+    // Each row is the curl formula applied to the spatial second derivatives
+    let mut jacobian = Matrix3::zeros();
+
+    for column in 0..3 {
+        jacobian[(0, column)] = hessian_3[(1, column)] - hessian_2[(2, column)];
+        jacobian[(1, column)] = hessian_1[(2, column)] - hessian_3[(0, column)];
+        jacobian[(2, column)] = hessian_2[(0, column)] - hessian_1[(1, column)];
+    }
+
+    (flow, jacobian)
+}
+
+/// Curvature of the streamline of curl_noise_3d going through this point, with the same tables.
+/// Points toward the center of the turn, its length is one over the turn radius.
+/// Around a vortex it points to the vortex core, in straight laminar lanes it goes to zero.
+pub fn streamline_curvature_3d(
+    coordinates: &Point3<f64>,
+    time: f64,
+    tables: &[PermutationTable; 3],
+) -> Vector3<f64> {
+    let (velocity, jacobian) = curl_noise_3d_with_jacobian(coordinates, time, tables);
+
+    let speed_sqr = velocity.norm_squared();
+
+    // No direction to turn from in a stagnation point
+    if speed_sqr < 1e-12 {
+        return Vector3::zeros();
+    }
+
+    // Acceleration of a particle following the flow, minus its part along the flow
+    let acceleration = jacobian * velocity;
+    let normal = acceleration - velocity * (acceleration.dot(&velocity) / speed_sqr);
+
+    normal / speed_sqr
+}
+
+/// Curl noise sliding around obstacles instead of crossing them.
+/// The distance field returns the distance to the closest surface and its gradient.
+/// The flow is unchanged further than the influence distance.
+pub fn curl_noise_3d_with_obstacles<F>(
+    coordinates: &Point3<f64>,
+    time: f64,
+    tables: &[PermutationTable; 3],
+    distance_field: F,
+    influence: f64,
+) -> Vector3<f64>
+where
+    F: Fn(&Point3<f64>) -> (f64, Vector3<f64>),
+{
+    let space_time = Point4::new(coordinates.x, coordinates.y, coordinates.z, time);
+
+    let (value_1, deriv_1) = simplex::with_derivatives_4d(&space_time, &tables[0]);
+    let (value_2, deriv_2) = simplex::with_derivatives_4d(&space_time, &tables[1]);
+    let (value_3, deriv_3) = simplex::with_derivatives_4d(&space_time, &tables[2]);
+
+    let derivatives = &Vector6::new(
+        deriv_1.y, deriv_1.z, deriv_2.x, deriv_2.z, deriv_3.x, deriv_3.y,
+    );
+
+    let (ramp, ramp_deriv) = obstacle_ramp(distance_field(coordinates), influence);
+
+    let potential = Vector3::new(value_1, value_2, value_3);
+
+    // curl(ramp * potential) = ramp * curl(potential) + grad(ramp) x potential
+    curl_3d(derivatives) * ramp + ramp_deriv.cross(&potential)
+}
+
 fn curl_3d(derivatives: &Vector6<f64>) -> Vector3<f64> {
     // potential field 3 deriv y - potential field 2 deriv z -> vector field x
     // potential field 1 deriv z - potential field 3 deriv x -> vector field y
@@ -47,3 +158,234 @@ fn curl_3d(derivatives: &Vector6<f64>) -> Vector3<f64> {
         derivatives.z - derivatives.x,
     )
 }
+
+/// Curl of fractal potentials, the flow stays divergence-free at any number of octaves.
+pub struct CurlField {
+    potentials: [Fractal; 3],
+    scale: f64,
+    time_rate: f64,
+    amplitude: f64,
+}
+
+impl CurlField {
+    /// Seeds of every octave are derived from the base seed.
+    /// Default scale, time rate and amplitude of 1.0.
+    pub fn new(seed: u64, octaves: usize) -> Self {
+        let potential = |index: i64| {
+            let seeds: Vec<u64> = (0..octaves as i64)
+                .map(|octave| hash_fonctions::hash_2d(index, octave, seed))
+                .collect();
+
+            Fractal::new(&seeds)
+        };
+
+        Self {
+            potentials: [potential(0), potential(1), potential(2)],
+            scale: 1.0,
+            time_rate: 1.0,
+            amplitude: 1.0,
+        }
+    }
+
+    /// Frequency of the first octave, bigger is smaller swirls.
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// How fast the flow changes over time.
+    pub fn with_time_rate(mut self, time_rate: f64) -> Self {
+        self.time_rate = time_rate;
+        self
+    }
+
+    /// Multiplier of the flow velocity.
+    pub fn with_amplitude(mut self, amplitude: f64) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    /// Frequency multiplier between octaves.
+    pub fn with_lacunarity(mut self, lacunarity: f64) -> Self {
+        self.potentials = self
+            .potentials
+            .map(|potential| potential.with_lacunarity(lacunarity));
+        self
+    }
+
+    /// Amplitude multiplier between octaves.
+    pub fn with_gain(mut self, gain: f64) -> Self {
+        self.potentials = self.potentials.map(|potential| potential.with_gain(gain));
+        self
+    }
+
+    pub fn sample_2d(&self, coordinates: &Point2<f64>, time: f64) -> Vector2<f64> {
+        let space_time = Point3::new(
+            coordinates.x * self.scale,
+            coordinates.y * self.scale,
+            time * self.time_rate,
+        );
+
+        let (_, derivatives) = self.potentials[0].fbm_3d(&space_time);
+
+        // Chain rule of the scaling
+        let derivatives = Vector2::new(derivatives.x, derivatives.y) * self.scale;
+
+        curl_2d(&derivatives) * self.amplitude
+    }
+
+    pub fn sample_3d(&self, coordinates: &Point3<f64>, time: f64) -> Vector3<f64> {
+        let space_time = Point4::new(
+            coordinates.x * self.scale,
+            coordinates.y * self.scale,
+            coordinates.z * self.scale,
+            time * self.time_rate,
+        );
+
+        let (_, deriv_1) = self.potentials[0].fbm_4d(&space_time);
+        let (_, deriv_2) = self.potentials[1].fbm_4d(&space_time);
+        let (_, deriv_3) = self.potentials[2].fbm_4d(&space_time);
+
+        let derivatives = &Vector6::new(
+            deriv_1.y, deriv_1.z, deriv_2.x, deriv_2.z, deriv_3.x, deriv_3.y,
+        );
+
+        // Chain rule of the scaling
+        curl_3d(derivatives) * (self.scale * self.amplitude)
+    }
+}
+
+/// Smooth ramp from 0 on the surface to 1 at the influence distance, and its gradient.
+fn obstacle_ramp<V>(distance: (f64, V), influence: f64) -> (f64, V)
+where
+    V: std::ops::Mul<f64, Output = V>,
+{
+    let (distance, gradient) = distance;
+
+    let r = distance / influence;
+
+    if r >= 1.0 {
+        return (1.0, gradient * 0.0);
+    }
+
+    if r <= -1.0 {
+        return (-1.0, gradient * 0.0);
+    }
+
+    let r2 = r * r;
+    let r4 = r2 * r2;
+
+    let ramp = (15.0 * r - 10.0 * r2 * r + 3.0 * r4 * r) / 8.0;
+    let slope = (15.0 - 30.0 * r2 + 15.0 * r4) / (8.0 * influence);
+
+    (ramp, gradient * slope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::test_utils::sample_positions;
+    use nalgebra::{U2, U3};
+
+    #[test]
+    fn obstacles() {
+        let tables = [
+            PermutationTable::from_seed(1),
+            PermutationTable::from_seed(2),
+            PermutationTable::from_seed(3),
+        ];
+
+        // Everything below the plane z = 0 is solid
+        let plane = |point: &Point3<f64>| (point.z, Vector3::z());
+
+        for position in sample_positions::<U3>(50) {
+            let surface = Point3::new(position.x, position.y, 0.0);
+
+            let flow = curl_noise_3d_with_obstacles(&surface, 0.5, &tables, plane, 2.0);
+
+            assert!(flow.z.abs() < 1e-12);
+
+            let far = Point3::new(surface.x, surface.y, 3.0);
+
+            let flow = curl_noise_3d_with_obstacles(&far, 0.5, &tables, plane, 2.0);
+
+            assert_eq!(flow, curl_noise_3d(&far, 0.5, &tables));
+        }
+
+        let line = |point: &Point2<f64>| (point.y, Vector2::y());
+
+        for position in sample_positions::<U2>(50) {
+            let surface = Point2::new(position.x, 0.0);
+
+            let flow = curl_noise_2d_with_obstacles(&surface, 0.5, &tables[0], line, 2.0);
+
+            assert!(flow.y.abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn divergence_free() {
+        let field = CurlField::new(7, 4)
+            .with_scale(0.3)
+            .with_time_rate(0.1)
+            .with_amplitude(2.0);
+
+        let step = 1e-5;
+
+        for position in sample_positions(50) {
+            let mut divergence = 0.0;
+
+            for axis in 0..3 {
+                let mut offset = Vector3::zeros();
+                offset[axis] = step;
+
+                let high = field.sample_3d(&(position + offset), 1.5);
+                let low = field.sample_3d(&(position - offset), 1.5);
+
+                divergence += (high[axis] - low[axis]) / (2.0 * step);
+            }
+
+            assert!(divergence.abs() < 1e-4);
+
+            let position = Point2::new(position.x, position.y);
+
+            let mut divergence = 0.0;
+
+            for axis in 0..2 {
+                let mut offset = Vector2::zeros();
+                offset[axis] = step;
+
+                let high = field.sample_2d(&(position + offset), 1.5);
+                let low = field.sample_2d(&(position - offset), 1.5);
+
+                divergence += (high[axis] - low[axis]) / (2.0 * step);
+            }
+
+            assert!(divergence.abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn streamline_curvature() {
+        let tables = [
+            PermutationTable::from_seed(4),
+            PermutationTable::from_seed(5),
+            PermutationTable::from_seed(6),
+        ];
+
+        let tangent = |point: &Point3<f64>| curl_noise_3d(point, 0.5, &tables).normalize();
+
+        let step = 1e-5;
+
+        for position in sample_positions(50) {
+            let curvature = streamline_curvature_3d(&position, 0.5, &tables);
+
+            // Turn of the unit tangent along the streamline
+            let forward = tangent(&position) * step;
+            let expected =
+                (tangent(&(position + forward)) - tangent(&(position - forward))) / (2.0 * step);
+
+            assert!((curvature - expected).norm() < 1e-4 * (1.0 + expected.norm()));
+        }
+    }
+}
@@ -0,0 +1,280 @@
+#![allow(dead_code)]
+
+use crate::noise::permutation_table::PermutationTable;
+use crate::noise::simplex;
+use nalgebra::{Point3, Point4};
+use nalgebra::{Vector3, Vector4};
+use std::ops::{Add, Mul};
+
+//https://www.iquilezles.org/www/articles/fbm/fbm.htm
+
+/// Layered simplex noise, one octave per permutation table.
+/// Results are normalized by the sum of amplitudes to stay in [-1, 1].
+pub struct Fractal {
+    tables: Vec<PermutationTable>,
+    lacunarity: f64,
+    gain: f64,
+}
+
+impl Fractal {
+    /// One octave per seed. Default lacunarity of 2.0 and gain of 0.5.
+    pub fn new(seeds: &[u64]) -> Self {
+        Self::from_tables(
+            seeds
+                .iter()
+                .map(|seed| PermutationTable::from_seed(*seed))
+                .collect(),
+        )
+    }
+
+    /// One octave per table. Default lacunarity of 2.0 and gain of 0.5.
+    pub fn from_tables(tables: Vec<PermutationTable>) -> Self {
+        Self {
+            tables,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+
+    /// Frequency multiplier between octaves.
+    pub fn with_lacunarity(mut self, lacunarity: f64) -> Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    /// Amplitude multiplier between octaves.
+    pub fn with_gain(mut self, gain: f64) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    pub fn octaves(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn fbm_3d(&self, position: &Point3<f64>) -> (f64, Vector3<f64>) {
+        self.layered(
+            position,
+            Vector3::zeros(),
+            simplex::with_derivatives_3d,
+            fbm,
+        )
+    }
+
+    pub fn fbm_4d(&self, position: &Point4<f64>) -> (f64, Vector4<f64>) {
+        self.layered(
+            position,
+            Vector4::zeros(),
+            simplex::with_derivatives_4d,
+            fbm,
+        )
+    }
+
+    /// Puffy noise, made from the absolute value of every octave.
+    pub fn billow_3d(&self, position: &Point3<f64>) -> (f64, Vector3<f64>) {
+        self.layered(
+            position,
+            Vector3::zeros(),
+            simplex::with_derivatives_3d,
+            billow,
+        )
+    }
+
+    /// Puffy noise, made from the absolute value of every octave.
+    pub fn billow_4d(&self, position: &Point4<f64>) -> (f64, Vector4<f64>) {
+        self.layered(
+            position,
+            Vector4::zeros(),
+            simplex::with_derivatives_4d,
+            billow,
+        )
+    }
+
+    /// Sharp ridges where the noise cross zero.
+    /// Each octave is weighted by the previous one so details gather on the ridges.
+    pub fn ridged_3d(&self, position: &Point3<f64>) -> (f64, Vector3<f64>) {
+        self.ridged(position, Vector3::zeros(), simplex::with_derivatives_3d)
+    }
+
+    /// Sharp ridges where the noise cross zero.
+    /// Each octave is weighted by the previous one so details gather on the ridges.
+    pub fn ridged_4d(&self, position: &Point4<f64>) -> (f64, Vector4<f64>) {
+        self.ridged(position, Vector4::zeros(), simplex::with_derivatives_4d)
+    }
+
+    fn layered<P, V>(
+        &self,
+        position: &P,
+        zero: V,
+        noise: impl Fn(&P, &PermutationTable) -> (f64, V),
+        shape: impl Fn(f64, V) -> (f64, V),
+    ) -> (f64, V)
+    where
+        P: Copy + Mul<f64, Output = P>,
+        V: Copy + Add<Output = V> + Mul<f64, Output = V>,
+    {
+        let mut value = 0.0;
+        let mut derivatives = zero;
+
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        let mut range = 0.0;
+
+        for table in self.tables.iter() {
+            let (sample, deriv) = noise(&(*position * frequency), table);
+            let (sample, deriv) = shape(sample, deriv);
+
+            value += sample * amplitude;
+
+            // Chain rule, the inner function is position * frequency
+            derivatives = derivatives + deriv * (amplitude * frequency);
+
+            range += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+
+        if range == 0.0 {
+            return (value, derivatives);
+        }
+
+        (value / range, derivatives * (1.0 / range))
+    }
+
+    fn ridged<P, V>(
+        &self,
+        position: &P,
+        zero: V,
+        noise: impl Fn(&P, &PermutationTable) -> (f64, V),
+    ) -> (f64, V)
+    where
+        P: Copy + Mul<f64, Output = P>,
+        V: Copy + Add<Output = V> + Mul<f64, Output = V>,
+    {
+        let mut value = 0.0;
+        let mut derivatives = zero;
+
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        let mut range = 0.0;
+
+        // Weight of the next octave and its derivatives
+        let mut weight = 1.0;
+        let mut weight_deriv = zero;
+
+        for table in self.tables.iter() {
+            let (sample, deriv) = noise(&(*position * frequency), table);
+
+            let ridge = 1.0 - sample.abs();
+
+            let signal = ridge * ridge * weight;
+            let signal_deriv = deriv * (-2.0 * ridge * sample.signum() * frequency * weight)
+                + weight_deriv * (ridge * ridge);
+
+            value += signal * amplitude;
+            derivatives = derivatives + signal_deriv * amplitude;
+
+            weight = signal * RIDGE_WEIGHT;
+            weight_deriv = signal_deriv * RIDGE_WEIGHT;
+
+            if weight > 1.0 {
+                weight = 1.0;
+                weight_deriv = zero;
+            } else if weight < 0.0 {
+                weight = 0.0;
+                weight_deriv = zero;
+            }
+
+            range += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+
+        if range == 0.0 {
+            return (value, derivatives);
+        }
+
+        // Scale from [0, range] to [-1, 1]
+        (value * 2.0 / range - 1.0, derivatives * (2.0 / range))
+    }
+}
+
+/// Strength of the ridges of one octave on the next.
+const RIDGE_WEIGHT: f64 = 2.0;
+
+fn fbm<V>(sample: f64, derivatives: V) -> (f64, V) {
+    (sample, derivatives)
+}
+
+fn billow<V: Mul<f64, Output = V>>(sample: f64, derivatives: V) -> (f64, V) {
+    (
+        2.0 * sample.abs() - 1.0,
+        derivatives * (2.0 * sample.signum()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::test_utils::check_derivatives;
+    use nalgebra::allocator::Allocator;
+    use nalgebra::{DefaultAllocator, DimName, Point, VectorN};
+
+    fn check_fractal<D: DimName>(noise: impl Fn(&Point<f64, D>) -> (f64, VectorN<f64, D>))
+    where
+        DefaultAllocator: Allocator<f64, D>,
+    {
+        check_derivatives(1e-4, |position| {
+            let (value, derivatives) = noise(position);
+
+            assert!(value.abs() <= 1.0);
+
+            (value, derivatives)
+        });
+    }
+
+    #[test]
+    fn fbm_derivatives() {
+        let fractal = Fractal::new(&[1, 2, 3, 4]).with_lacunarity(1.9);
+
+        check_fractal(|position: &Point3<f64>| fractal.fbm_3d(position));
+        check_fractal(|position: &Point4<f64>| fractal.fbm_4d(position));
+    }
+
+    #[test]
+    fn billow_derivatives() {
+        // Billow is creased where an octave crosses zero, at lacunarity 2 the first 3D position
+        // lands on a zero of the second octave
+        let fractal = Fractal::new(&[5, 6, 7]).with_gain(0.6).with_lacunarity(1.9);
+
+        check_fractal(|position: &Point3<f64>| fractal.billow_3d(position));
+        check_fractal(|position: &Point4<f64>| fractal.billow_4d(position));
+    }
+
+    #[test]
+    fn ridged_derivatives() {
+        let fractal = Fractal::new(&[8, 9, 10]);
+
+        check_fractal(|position: &Point3<f64>| fractal.ridged_3d(position));
+        check_fractal(|position: &Point4<f64>| fractal.ridged_4d(position));
+    }
+
+    #[test]
+    fn single_octave_3d() {
+        let fractal = Fractal::new(&[11]);
+        let table = PermutationTable::from_seed(11);
+
+        for index in 0..100 {
+            let position = Point3::new(index as f64 * 0.37, index as f64 * -0.23, 1.5);
+
+            let (sample, deriv) = simplex::with_derivatives_3d(&position, &table);
+
+            assert_eq!(fractal.fbm_3d(&position), (sample, deriv));
+
+            let (value, derivatives) = fractal.billow_3d(&position);
+
+            assert!((value - (2.0 * sample.abs() - 1.0)).abs() < 1e-12);
+            assert!((derivatives - deriv * (2.0 * sample.signum())).norm() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,380 @@
+#![allow(dead_code)]
+
+use crate::noise::combinators::{Add, Blend, Clamp, Multiply, ScaleBias, Select, Transform};
+use crate::noise::fractal::Fractal;
+use crate::noise::noise_fn::{Billow, Constant, Domain, Fbm, NoiseFn, Ridged, Simplex};
+use crate::noise::permutation_table::PermutationTable;
+use nalgebra::{Matrix3, Matrix4};
+use nalgebra::{Point3, Point4};
+use nalgebra::{Vector3, Vector4};
+use serde::{Deserialize, Serialize};
+
+/*
+    Noise graphs described in text files.
+    egg. in RON
+    ScaleBias(
+        source: Fractal(kind: Ridged, seeds: [1, 2, 3]),
+        scale: 0.5,
+        bias: 0.5,
+    )
+*/
+
+#[derive(Debug, PartialEq)]
+pub enum GraphError {
+    Parse(String),
+    Serialize(String),
+    /// A fractal needs at least one seed
+    NoOctaves,
+    /// Every number must be finite
+    NotFinite,
+    /// Clamp min is greater than max
+    InvalidClamp,
+    /// Select falloff is negative
+    NegativeFalloff,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum FractalKind {
+    Fbm,
+    Billow,
+    Ridged,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum NoiseDef {
+    Constant(f64),
+    Simplex {
+        seed: u64,
+    },
+    Fractal {
+        kind: FractalKind,
+        seeds: Vec<u64>,
+        #[serde(default = "default_lacunarity")]
+        lacunarity: f64,
+        #[serde(default = "default_gain")]
+        gain: f64,
+    },
+    Add(Box<NoiseDef>, Box<NoiseDef>),
+    Multiply(Box<NoiseDef>, Box<NoiseDef>),
+    ScaleBias {
+        source: Box<NoiseDef>,
+        scale: f64,
+        bias: f64,
+    },
+    Clamp {
+        source: Box<NoiseDef>,
+        min: f64,
+        max: f64,
+    },
+    Select {
+        a: Box<NoiseDef>,
+        b: Box<NoiseDef>,
+        control: Box<NoiseDef>,
+        threshold: f64,
+        #[serde(default)]
+        falloff: f64,
+    },
+    Blend {
+        a: Box<NoiseDef>,
+        b: Box<NoiseDef>,
+        control: Box<NoiseDef>,
+    },
+    /// Per axis scale then translation, the 4th axis is ignored in 3D.
+    Transform {
+        source: Box<NoiseDef>,
+        scale: [f64; 4],
+        #[serde(default)]
+        translation: [f64; 4],
+    },
+}
+
+fn default_lacunarity() -> f64 {
+    2.0
+}
+
+fn default_gain() -> f64 {
+    0.5
+}
+
+impl NoiseDef {
+    pub fn from_ron(text: &str) -> Result<Self, GraphError> {
+        let def: Self = ron::from_str(text).map_err(|e| GraphError::Parse(e.to_string()))?;
+
+        def.validate()?;
+
+        Ok(def)
+    }
+
+    pub fn to_ron(&self) -> Result<String, GraphError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| GraphError::Serialize(e.to_string()))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, GraphError> {
+        let def: Self = serde_json::from_str(text).map_err(|e| GraphError::Parse(e.to_string()))?;
+
+        def.validate()?;
+
+        Ok(def)
+    }
+
+    pub fn to_json(&self) -> Result<String, GraphError> {
+        serde_json::to_string_pretty(self).map_err(|e| GraphError::Serialize(e.to_string()))
+    }
+
+    /// Check the whole graph for values that can't be evaluated.
+    pub fn validate(&self) -> Result<(), GraphError> {
+        match self {
+            NoiseDef::Constant(value) => finite(&[*value]),
+            NoiseDef::Simplex { .. } => Ok(()),
+            NoiseDef::Fractal {
+                seeds,
+                lacunarity,
+                gain,
+                ..
+            } => {
+                if seeds.is_empty() {
+                    return Err(GraphError::NoOctaves);
+                }
+
+                finite(&[*lacunarity, *gain])
+            }
+            NoiseDef::Add(a, b) | NoiseDef::Multiply(a, b) => {
+                a.validate()?;
+                b.validate()
+            }
+            NoiseDef::ScaleBias {
+                source,
+                scale,
+                bias,
+            } => {
+                finite(&[*scale, *bias])?;
+                source.validate()
+            }
+            NoiseDef::Clamp { source, min, max } => {
+                finite(&[*min, *max])?;
+
+                if min > max {
+                    return Err(GraphError::InvalidClamp);
+                }
+
+                source.validate()
+            }
+            NoiseDef::Select {
+                a,
+                b,
+                control,
+                threshold,
+                falloff,
+            } => {
+                finite(&[*threshold, *falloff])?;
+
+                if *falloff < 0.0 {
+                    return Err(GraphError::NegativeFalloff);
+                }
+
+                a.validate()?;
+                b.validate()?;
+                control.validate()
+            }
+            NoiseDef::Blend { a, b, control } => {
+                a.validate()?;
+                b.validate()?;
+                control.validate()
+            }
+            NoiseDef::Transform {
+                source,
+                scale,
+                translation,
+            } => {
+                finite(scale)?;
+                finite(translation)?;
+                source.validate()
+            }
+        }
+    }
+
+    pub fn build_3d(&self) -> Result<Box<dyn NoiseFn<Point3<f64>>>, GraphError> {
+        self.validate()?;
+
+        Ok(self.build())
+    }
+
+    pub fn build_4d(&self) -> Result<Box<dyn NoiseFn<Point4<f64>>>, GraphError> {
+        self.validate()?;
+
+        Ok(self.build())
+    }
+
+    fn build<P: GraphDomain>(&self) -> Box<dyn NoiseFn<P>> {
+        match self {
+            NoiseDef::Constant(value) => Box::new(Constant(*value)),
+            NoiseDef::Simplex { seed } => P::simplex(PermutationTable::from_seed(*seed)),
+            NoiseDef::Fractal {
+                kind,
+                seeds,
+                lacunarity,
+                gain,
+            } => {
+                let fractal = Fractal::new(seeds)
+                    .with_lacunarity(*lacunarity)
+                    .with_gain(*gain);
+
+                P::fractal(*kind, fractal)
+            }
+            NoiseDef::Add(a, b) => Box::new(Add::new(a.build::<P>(), b.build::<P>())),
+            NoiseDef::Multiply(a, b) => Box::new(Multiply::new(a.build::<P>(), b.build::<P>())),
+            NoiseDef::ScaleBias {
+                source,
+                scale,
+                bias,
+            } => Box::new(ScaleBias::new(source.build::<P>(), *scale, *bias)),
+            NoiseDef::Clamp { source, min, max } => {
+                Box::new(Clamp::new(source.build::<P>(), *min, *max))
+            }
+            NoiseDef::Select {
+                a,
+                b,
+                control,
+                threshold,
+                falloff,
+            } => Box::new(Select::new(
+                a.build::<P>(),
+                b.build::<P>(),
+                control.build::<P>(),
+                *threshold,
+                *falloff,
+            )),
+            NoiseDef::Blend { a, b, control } => Box::new(Blend::new(
+                a.build::<P>(),
+                b.build::<P>(),
+                control.build::<P>(),
+            )),
+            NoiseDef::Transform {
+                source,
+                scale,
+                translation,
+            } => Box::new(Transform::new(
+                source.build::<P>(),
+                P::scale_matrix(scale),
+                P::translation(translation),
+            )),
+        }
+    }
+}
+
+fn finite(values: &[f64]) -> Result<(), GraphError> {
+    if values.iter().all(|value| value.is_finite()) {
+        Ok(())
+    } else {
+        Err(GraphError::NotFinite)
+    }
+}
+
+/// Domains every node of a graph can be evaluated in.
+trait GraphDomain: Domain + 'static {
+    fn simplex(table: PermutationTable) -> Box<dyn NoiseFn<Self>>;
+
+    fn fractal(kind: FractalKind, fractal: Fractal) -> Box<dyn NoiseFn<Self>>;
+
+    fn scale_matrix(scale: &[f64; 4]) -> Self::Matrix;
+
+    fn translation(translation: &[f64; 4]) -> Self::Gradient;
+}
+
+impl GraphDomain for Point3<f64> {
+    fn simplex(table: PermutationTable) -> Box<dyn NoiseFn<Self>> {
+        Box::new(Simplex::new(table))
+    }
+
+    fn fractal(kind: FractalKind, fractal: Fractal) -> Box<dyn NoiseFn<Self>> {
+        match kind {
+            FractalKind::Fbm => Box::new(Fbm(fractal)),
+            FractalKind::Billow => Box::new(Billow(fractal)),
+            FractalKind::Ridged => Box::new(Ridged(fractal)),
+        }
+    }
+
+    fn scale_matrix(scale: &[f64; 4]) -> Matrix3<f64> {
+        Matrix3::from_diagonal(&Vector3::new(scale[0], scale[1], scale[2]))
+    }
+
+    fn translation(translation: &[f64; 4]) -> Vector3<f64> {
+        Vector3::new(translation[0], translation[1], translation[2])
+    }
+}
+
+impl GraphDomain for Point4<f64> {
+    fn simplex(table: PermutationTable) -> Box<dyn NoiseFn<Self>> {
+        Box::new(Simplex::new(table))
+    }
+
+    fn fractal(kind: FractalKind, fractal: Fractal) -> Box<dyn NoiseFn<Self>> {
+        match kind {
+            FractalKind::Fbm => Box::new(Fbm(fractal)),
+            FractalKind::Billow => Box::new(Billow(fractal)),
+            FractalKind::Ridged => Box::new(Ridged(fractal)),
+        }
+    }
+
+    fn scale_matrix(scale: &[f64; 4]) -> Matrix4<f64> {
+        Matrix4::from_diagonal(&Vector4::from_column_slice(scale))
+    }
+
+    fn translation(translation: &[f64; 4]) -> Vector4<f64> {
+        Vector4::from_column_slice(translation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAPH: &str = "
+        Select(
+            a: Fractal(kind: Ridged, seeds: [1, 2, 3]),
+            b: ScaleBias(source: Simplex(seed: 4), scale: 0.5, bias: -0.25),
+            control: Transform(
+                source: Fractal(kind: Fbm, seeds: [5, 6], gain: 0.6),
+                scale: (0.1, 0.1, 0.1, 1.0),
+            ),
+            threshold: 0.2,
+            falloff: 0.1,
+        )
+    ";
+
+    #[test]
+    fn round_trip() {
+        let def = NoiseDef::from_ron(GRAPH).unwrap();
+
+        assert_eq!(NoiseDef::from_ron(&def.to_ron().unwrap()).unwrap(), def);
+        assert_eq!(NoiseDef::from_json(&def.to_json().unwrap()).unwrap(), def);
+    }
+
+    #[test]
+    fn evaluate() {
+        let def = NoiseDef::from_ron(GRAPH).unwrap();
+
+        let noise_3d = def.build_3d().unwrap();
+        let noise_4d = def.build_4d().unwrap();
+
+        let (value_3d, _) = noise_3d.sample(&Point3::new(1.5, -2.0, 0.3));
+        let (value_4d, _) = noise_4d.sample(&Point4::new(1.5, -2.0, 0.3, 7.0));
+
+        assert!(value_3d.is_finite());
+        assert!(value_4d.is_finite());
+    }
+
+    #[test]
+    fn invalid() {
+        let empty = "Fractal(kind: Billow, seeds: [])";
+        let clamp = "Clamp(source: Constant(1.0), min: 1.0, max: -1.0)";
+
+        assert_eq!(NoiseDef::from_ron(empty), Err(GraphError::NoOctaves));
+        assert_eq!(NoiseDef::from_ron(clamp), Err(GraphError::InvalidClamp));
+        assert!(matches!(
+            NoiseDef::from_ron("Simplex()"),
+            Err(GraphError::Parse(_))
+        ));
+    }
+}
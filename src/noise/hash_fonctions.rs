@@ -2,26 +2,73 @@
 
 use nalgebra::Point3;
 
-//TODO find better coordinates hashing functions
+// Integer lattice hashes, xxhash style.
+// Only integer operations so results are the same on every platform.
 
-pub fn coordinates_hash_x(position: Point3<f64>) -> f64 {
-    (((position.x * 11.4 * 5.0) + (position.y * 6.5 * 11.0) + (position.z * 223.5 * 7.0)).sin()
-        * -1294.0)
-        % 1.0
+pub fn hash_2d(x: i64, y: i64, seed: u64) -> u64 {
+    let mut hash = seed.wrapping_add(XX_PRIME_5);
+
+    hash = xx_round(hash, x as u64);
+    hash = xx_round(hash, y as u64);
+
+    xx_avalanche(hash)
+}
+
+pub fn hash_3d(x: i64, y: i64, z: i64, seed: u64) -> u64 {
+    let mut hash = seed.wrapping_add(XX_PRIME_5);
+
+    hash = xx_round(hash, x as u64);
+    hash = xx_round(hash, y as u64);
+    hash = xx_round(hash, z as u64);
+
+    xx_avalanche(hash)
 }
 
-pub fn coordinates_hash_y(position: Point3<f64>) -> f64 {
-    (((position.x * 57.2 * 5.0) + (position.y * 209.9 * 11.0) + (position.z * -33.1 * 7.0)).sin()
-        * -19494.0)
-        % 1.0
+/// Map a hash to [0, 1).
+pub fn hash_to_unit(hash: u64) -> f64 {
+    // The 53 high bits fill the mantissa exactly.
+    (hash >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+}
+
+/// SplitMix64 finalizer, a bijection scattering every bit.
+/// Turns sparse or sequential IDs into well spread seeds.
+pub fn split_mix_64(mut value: u64) -> u64 {
+    value ^= value >> 30;
+    value = value.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value ^= value >> 27;
+    value = value.wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+fn xx_round(accumulator: u64, input: u64) -> u64 {
+    let accumulator = accumulator
+        ^ input
+            .wrapping_mul(XX_PRIME_2)
+            .rotate_left(31)
+            .wrapping_mul(XX_PRIME_1);
+
+    accumulator
+        .rotate_left(27)
+        .wrapping_mul(XX_PRIME_1)
+        .wrapping_add(XX_PRIME_4)
 }
 
-pub fn coordinates_hash_z(position: Point3<f64>) -> f64 {
-    (((position.x * -85.3 * 5.0) + (position.y * -23.6 * 11.0) + (position.z * -8.7 * 7.0)).sin()
-        * 11372.0)
-        % 1.0
+fn xx_avalanche(mut hash: u64) -> u64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(XX_PRIME_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(XX_PRIME_3);
+    hash ^= hash >> 32;
+
+    hash
 }
 
+const XX_PRIME_1: u64 = 0x9e37_79b1_85eb_ca87;
+const XX_PRIME_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const XX_PRIME_3: u64 = 0x1656_67b1_9e37_79f9;
+const XX_PRIME_4: u64 = 0x85eb_ca77_c2b2_ae63;
+const XX_PRIME_5: u64 = 0x27d4_eb2f_1656_67c5;
+
 pub fn coordinates_hash_fibonnaci(position: Point3<f64>) -> u64 {
     (position.x.to_bits() + position.y.to_bits() + position.z.to_bits())
         .wrapping_mul(FIBONNACI_MAGIC_NUMBER_64BIT)
@@ -46,3 +93,29 @@ pub fn fibonacci_hash_16_bit(input: u32) -> u16 {
 pub fn fibonacci_hash_8_bit(input: u16) -> u8 {
     ((input.wrapping_mul(FIBONNACI_MAGIC_NUMBER_16BIT)) >> 8) as u8
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lattice_hashes() {
+        let mut previous = 0;
+
+        for x in -50..50 {
+            let hash = hash_3d(x, -x * 7, i64::MAX - 50 - x, 42);
+
+            assert_eq!(hash, hash_3d(x, -x * 7, i64::MAX - 50 - x, 42));
+            assert_ne!(hash, hash_3d(x, -x * 7, i64::MAX - 50 - x, 43));
+            assert_ne!(hash, previous);
+
+            let unit = hash_to_unit(hash);
+
+            assert!((0.0..1.0).contains(&unit));
+
+            previous = hash;
+        }
+
+        assert!(hash_to_unit(u64::MAX) < 1.0);
+    }
+}
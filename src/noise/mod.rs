@@ -1,4 +1,15 @@
+pub mod batch;
+pub mod cellular;
+pub mod combinators;
 pub mod curl;
-mod hash_fonctions;
+pub mod fractal;
+pub mod graph;
+pub(crate) mod hash_fonctions;
+pub mod noise_fn;
+pub mod permutation_table;
 pub mod simplex;
 pub mod smooth_voronoi;
+pub mod statistics;
+#[cfg(test)]
+mod test_utils;
+pub mod warp;
@@ -0,0 +1,254 @@
+#![allow(dead_code)]
+
+use crate::noise::combinators::{Add, Blend, Clamp, Multiply, ScaleBias, Select, Transform};
+use crate::noise::fractal::Fractal;
+use crate::noise::permutation_table::PermutationTable;
+use crate::noise::simplex;
+use crate::noise::smooth_voronoi;
+use nalgebra::{Matrix2, Matrix3, Matrix4};
+use nalgebra::{Point2, Point3, Point4};
+use nalgebra::{Vector2, Vector3, Vector4};
+use std::ops;
+
+/// Point type a noise can be sampled at.
+pub trait Domain: Copy {
+    type Gradient: Copy + ops::Add<Output = Self::Gradient> + ops::Mul<f64, Output = Self::Gradient>;
+    type Matrix: Copy;
+
+    fn zero_gradient() -> Self::Gradient;
+
+    /// Matrix times point plus translation.
+    fn transform(&self, matrix: &Self::Matrix, translation: &Self::Gradient) -> Self;
+
+    /// Chain rule of a linear transform, the transposed matrix times the gradient.
+    fn pull_back(matrix: &Self::Matrix, gradient: &Self::Gradient) -> Self::Gradient;
+}
+
+impl Domain for Point2<f64> {
+    type Gradient = Vector2<f64>;
+    type Matrix = Matrix2<f64>;
+
+    fn zero_gradient() -> Vector2<f64> {
+        Vector2::zeros()
+    }
+
+    fn transform(&self, matrix: &Matrix2<f64>, translation: &Vector2<f64>) -> Self {
+        matrix * self + translation
+    }
+
+    fn pull_back(matrix: &Matrix2<f64>, gradient: &Vector2<f64>) -> Vector2<f64> {
+        matrix.tr_mul(gradient)
+    }
+}
+
+impl Domain for Point3<f64> {
+    type Gradient = Vector3<f64>;
+    type Matrix = Matrix3<f64>;
+
+    fn zero_gradient() -> Vector3<f64> {
+        Vector3::zeros()
+    }
+
+    fn transform(&self, matrix: &Matrix3<f64>, translation: &Vector3<f64>) -> Self {
+        matrix * self + translation
+    }
+
+    fn pull_back(matrix: &Matrix3<f64>, gradient: &Vector3<f64>) -> Vector3<f64> {
+        matrix.tr_mul(gradient)
+    }
+}
+
+impl Domain for Point4<f64> {
+    type Gradient = Vector4<f64>;
+    type Matrix = Matrix4<f64>;
+
+    fn zero_gradient() -> Vector4<f64> {
+        Vector4::zeros()
+    }
+
+    fn transform(&self, matrix: &Matrix4<f64>, translation: &Vector4<f64>) -> Self {
+        matrix * self + translation
+    }
+
+    fn pull_back(matrix: &Matrix4<f64>, gradient: &Vector4<f64>) -> Vector4<f64> {
+        matrix.tr_mul(gradient)
+    }
+}
+
+/// Anything returning a value and its derivatives at a point.
+pub trait NoiseFn<P: Domain> {
+    fn sample(&self, point: &P) -> (f64, P::Gradient);
+}
+
+/// Marker of the noise types, only they get the NoiseFnExt combinators.
+/// Not tied to a domain so the domain is only decided when sampling.
+pub trait Noise {}
+
+/// Build noise graphs by chaining combinators.
+pub trait NoiseFnExt: Noise + Sized {
+    fn add<N>(self, other: N) -> Add<Self, N> {
+        Add::new(self, other)
+    }
+
+    fn multiply<N>(self, other: N) -> Multiply<Self, N> {
+        Multiply::new(self, other)
+    }
+
+    fn scale_bias(self, scale: f64, bias: f64) -> ScaleBias<Self> {
+        ScaleBias::new(self, scale, bias)
+    }
+
+    fn clamp(self, min: f64, max: f64) -> Clamp<Self> {
+        Clamp::new(self, min, max)
+    }
+
+    /// Self where control is below the threshold, other above.
+    fn select<N, C>(
+        self,
+        other: N,
+        control: C,
+        threshold: f64,
+        falloff: f64,
+    ) -> Select<Self, N, C> {
+        Select::new(self, other, control, threshold, falloff)
+    }
+
+    /// Self where control is -1, other where control is 1 and a mix in between.
+    fn blend<N, C>(self, other: N, control: C) -> Blend<Self, N, C> {
+        Blend::new(self, other, control)
+    }
+
+    /// Sample at matrix times point plus translation.
+    fn transform<M, T>(self, matrix: M, translation: T) -> Transform<Self, M, T> {
+        Transform::new(self, matrix, translation)
+    }
+}
+
+impl<T: Noise> NoiseFnExt for T {}
+
+impl<N: Noise + ?Sized> Noise for &N {}
+
+impl<N: Noise + ?Sized> Noise for Box<N> {}
+
+impl<P: Domain> Noise for dyn NoiseFn<P> {}
+
+impl<P: Domain, N: NoiseFn<P> + ?Sized> NoiseFn<P> for &N {
+    fn sample(&self, point: &P) -> (f64, P::Gradient) {
+        (**self).sample(point)
+    }
+}
+
+impl<P: Domain, N: NoiseFn<P> + ?Sized> NoiseFn<P> for Box<N> {
+    fn sample(&self, point: &P) -> (f64, P::Gradient) {
+        (**self).sample(point)
+    }
+}
+
+/// Same value everywhere.
+pub struct Constant(pub f64);
+
+impl Noise for Constant {}
+
+impl<P: Domain> NoiseFn<P> for Constant {
+    fn sample(&self, _: &P) -> (f64, P::Gradient) {
+        (self.0, P::zero_gradient())
+    }
+}
+
+pub struct Simplex {
+    table: PermutationTable,
+}
+
+impl Noise for Simplex {}
+
+impl Simplex {
+    pub fn new(table: PermutationTable) -> Self {
+        Self { table }
+    }
+}
+
+impl NoiseFn<Point2<f64>> for Simplex {
+    fn sample(&self, point: &Point2<f64>) -> (f64, Vector2<f64>) {
+        simplex::with_derivatives_2d(point, &self.table)
+    }
+}
+
+impl NoiseFn<Point3<f64>> for Simplex {
+    fn sample(&self, point: &Point3<f64>) -> (f64, Vector3<f64>) {
+        simplex::with_derivatives_3d(point, &self.table)
+    }
+}
+
+impl NoiseFn<Point4<f64>> for Simplex {
+    fn sample(&self, point: &Point4<f64>) -> (f64, Vector4<f64>) {
+        simplex::with_derivatives_4d(point, &self.table)
+    }
+}
+
+pub struct Fbm(pub Fractal);
+
+impl Noise for Fbm {}
+
+impl NoiseFn<Point3<f64>> for Fbm {
+    fn sample(&self, point: &Point3<f64>) -> (f64, Vector3<f64>) {
+        self.0.fbm_3d(point)
+    }
+}
+
+impl NoiseFn<Point4<f64>> for Fbm {
+    fn sample(&self, point: &Point4<f64>) -> (f64, Vector4<f64>) {
+        self.0.fbm_4d(point)
+    }
+}
+
+pub struct Billow(pub Fractal);
+
+impl Noise for Billow {}
+
+impl NoiseFn<Point3<f64>> for Billow {
+    fn sample(&self, point: &Point3<f64>) -> (f64, Vector3<f64>) {
+        self.0.billow_3d(point)
+    }
+}
+
+impl NoiseFn<Point4<f64>> for Billow {
+    fn sample(&self, point: &Point4<f64>) -> (f64, Vector4<f64>) {
+        self.0.billow_4d(point)
+    }
+}
+
+pub struct Ridged(pub Fractal);
+
+impl Noise for Ridged {}
+
+impl NoiseFn<Point3<f64>> for Ridged {
+    fn sample(&self, point: &Point3<f64>) -> (f64, Vector3<f64>) {
+        self.0.ridged_3d(point)
+    }
+}
+
+impl NoiseFn<Point4<f64>> for Ridged {
+    fn sample(&self, point: &Point4<f64>) -> (f64, Vector4<f64>) {
+        self.0.ridged_4d(point)
+    }
+}
+
+pub struct SmoothVoronoi {
+    pub scale: f64,
+    pub hardness: f64,
+    pub seed: u64,
+}
+
+impl Noise for SmoothVoronoi {}
+
+impl NoiseFn<Point2<f64>> for SmoothVoronoi {
+    fn sample(&self, point: &Point2<f64>) -> (f64, Vector2<f64>) {
+        smooth_voronoi::smooth_voronoi_2d(*point, self.scale, self.hardness, self.seed)
+    }
+}
+
+impl NoiseFn<Point3<f64>> for SmoothVoronoi {
+    fn sample(&self, point: &Point3<f64>) -> (f64, Vector3<f64>) {
+        smooth_voronoi::smooth_voronoi_3d(*point, self.scale, self.hardness, self.seed)
+    }
+}
@@ -0,0 +1,92 @@
+#![allow(dead_code)]
+
+use crate::id_types::DatabaseId;
+use rand::Rng;
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256StarStar;
+use std::ops::Index;
+
+/// Shuffled permutation of 0..=255 repeated twice.
+/// Every noise layer should have its own table.
+#[derive(Clone)]
+pub struct PermutationTable {
+    values: [u8; 512],
+}
+
+impl PermutationTable {
+    pub fn from_seed(seed: u64) -> Self {
+        Self::from_rng(Xoshiro256StarStar::seed_from_u64(seed))
+    }
+
+    pub fn from_database_id(id: &DatabaseId) -> Self {
+        let mut seed = [0u8; 32];
+
+        seed[..20].copy_from_slice(id.as_bytes());
+
+        Self::from_rng(Xoshiro256StarStar::from_seed(seed))
+    }
+
+    fn from_rng(mut rng: Xoshiro256StarStar) -> Self {
+        let mut permutation = [0u8; 256];
+
+        for (index, value) in permutation.iter_mut().enumerate() {
+            *value = index as u8;
+        }
+
+        // Fisher-Yates shuffle
+        for index in (1..permutation.len()).rev() {
+            let other = rng.gen_range(0, index + 1);
+
+            permutation.swap(index, other);
+        }
+
+        let mut values = [0u8; 512];
+
+        values[..256].copy_from_slice(&permutation);
+        values[256..].copy_from_slice(&permutation);
+
+        Self { values }
+    }
+}
+
+impl Index<usize> for PermutationTable {
+    type Output = u8;
+
+    fn index(&self, index: usize) -> &u8 {
+        &self.values[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_permutation() {
+        let table = PermutationTable::from_seed(7);
+
+        let mut seen = [false; 256];
+
+        for index in 0..256 {
+            assert_eq!(table[index], table[index + 256]);
+
+            seen[table[index] as usize] = true;
+        }
+
+        assert!(seen.iter().all(|seen| *seen));
+    }
+
+    #[test]
+    fn deterministic() {
+        let id = DatabaseId::from_string("a1B2c3D4").unwrap();
+
+        let first = PermutationTable::from_database_id(&id);
+        let second = PermutationTable::from_database_id(&id);
+
+        assert_eq!(&first.values[..], &second.values[..]);
+        assert_ne!(
+            &first.values[..],
+            &PermutationTable::from_seed(7).values[..]
+        );
+    }
+}
@@ -20,37 +20,181 @@
 
 // https://github.com/stegu/perlin-noise
 
-use nalgebra::{Point3, Point4};
-use nalgebra::{Vector3, Vector4};
+use crate::noise::permutation_table::PermutationTable;
+use nalgebra::{Matrix3, Matrix4};
+use nalgebra::{Point2, Point3, Point4};
+use nalgebra::{Vector2, Vector3, Vector4};
 
-pub fn with_derivatives_4d(position: &Point4<f64>, seed: &[u8; 512]) -> (f64, Vector4<f64>) {
-    let mut offsets = [Vector4::zeros(); 5];
+pub fn with_derivatives_4d(
+    position: &Point4<f64>,
+    table: &PermutationTable,
+) -> (f64, Vector4<f64>) {
+    let (cell, offset) = skew_4d(position.x, position.y, position.z, position.w);
 
+    let cell = [
+        cell[0] as i64,
+        cell[1] as i64,
+        cell[2] as i64,
+        cell[3] as i64,
+    ];
+
+    sum_corners_4d(cell, offset, table)
+}
+
+/// Same as with_derivatives_4d at origin + offset.
+/// The integer origin keeps the precision far away from zero, only the offset should stay small.
+/// Origin components must stay within +/- 2^59.
+pub fn with_derivatives_4d_from_origin(
+    origin: &Point4<i64>,
+    offset: &Vector4<f64>,
+    table: &PermutationTable,
+) -> (f64, Vector4<f64>) {
+    debug_assert!(
+        origin.iter().all(|c| c.unsigned_abs() <= 1 << 59),
+        "origin out of the +/- 2^59 range"
+    );
+
+    // Skewing the origin is split in a whole part and a fraction computed in fixed point.
+    let sum = origin.x as i128 + origin.y as i128 + origin.z as i128 + origin.w as i128;
+
+    let skewed = sum * F4_HIGH as i128 + ((sum * F4_LOW as i128) >> 64);
+
+    let whole = (skewed >> 64) as i64;
+    let fraction = (skewed as u64) as f64 / TWO_POW_64;
+
+    let skew_factor = F4 * offset.x + F4 * offset.y + F4 * offset.z + F4 * offset.w + fraction;
+
+    let i = (offset.x + skew_factor).floor();
+    let j = (offset.y + skew_factor).floor();
+    let k = (offset.z + skew_factor).floor();
+    let l = (offset.w + skew_factor).floor();
+
+    // The unskewed whole part cancels out with the origin, only the fraction remains.
+    let unskew_factor = G4 * i + G4 * j + G4 * k + G4 * l + fraction * ORIGIN_UNSKEW_4D;
+
+    let cell = [
+        origin.x + whole + i as i64,
+        origin.y + whole + j as i64,
+        origin.z + whole + k as i64,
+        origin.w + whole + l as i64,
+    ];
+
+    let offset = [
+        offset.x - (i - unskew_factor),
+        offset.y - (j - unskew_factor),
+        offset.z - (k - unskew_factor),
+        offset.w - (l - unskew_factor),
+    ];
+
+    sum_corners_4d(cell, offset, table)
+}
+
+/// Same as with_derivatives_4d with the second derivatives added.
+pub fn with_hessian_4d(
+    position: &Point4<f64>,
+    table: &PermutationTable,
+) -> (f64, Vector4<f64>, Matrix4<f64>) {
+    let (cell, offset) = skew_4d(position.x, position.y, position.z, position.w);
+
+    let cell = [
+        cell[0] as i64,
+        cell[1] as i64,
+        cell[2] as i64,
+        cell[3] as i64,
+    ];
+
+    let (offsets, gradients) = corners_4d(cell, offset, table);
+
+    let mut n = 0.0;
+    let mut derivatives = Vector4::zeros();
+    let mut hessian = Matrix4::zeros();
+
+    for (offset, gradient) in offsets.iter().zip(gradients.iter()) {
+        let offset = Vector4::from(*offset);
+        let gradient = Vector4::from(*gradient);
+
+        let t = (0.5 - offset.norm_squared()).max(0.0);
+
+        let t2 = t * t;
+        let t3 = t2 * t;
+        let t4 = t2 * t2;
+
+        let grad_dot = gradient.dot(&offset);
+
+        n += t4 * grad_dot;
+        derivatives += offset * (-8.0 * t3 * grad_dot) + gradient * t4;
+
+        let cross = offset * gradient.transpose();
+
+        hessian += offset * offset.transpose() * (48.0 * t2 * grad_dot)
+            - (cross + cross.transpose()) * (8.0 * t3)
+            - Matrix4::identity() * (8.0 * t3 * grad_dot);
+    }
+
+    (
+        n * NORMALIZE_4D,
+        derivatives * NORMALIZE_4D,
+        hessian * NORMALIZE_4D,
+    )
+}
+
+#[inline(always)]
+fn sum_corners_4d(
+    cell: [i64; 4],
+    offset: [f64; 4],
+    table: &PermutationTable,
+) -> (f64, Vector4<f64>) {
+    let (offsets, gradients) = corners_4d(cell, offset, table);
+
+    let mut n = 0.0;
+    let mut derivatives = [0.0; 4];
+
+    for (offset, gradient) in offsets.iter().zip(gradients.iter()) {
+        let (value, deriv) = falloff_4d(offset, gradient);
+
+        n += value;
+
+        for (total, deriv) in derivatives.iter_mut().zip(deriv.iter()) {
+            *total += deriv;
+        }
+    }
+
+    (n * NORMALIZE_4D, Vector4::from(derivatives) * NORMALIZE_4D)
+}
+
+/// Skew the (x,y,z,w) space to determine which cell of 24 simplices we're in.
+/// Return the floored cell and the x,y,z,w distances from the cell origin.
+#[inline(always)]
+pub(crate) fn skew_4d(x: f64, y: f64, z: f64, w: f64) -> ([f64; 4], [f64; 4]) {
     // Factor for 4D skewing
-    let skew_factor = F4 * position.x + F4 * position.y + F4 * position.z + F4 * position.w; // Multiplying before adding keep values smaller, adding precision but is slower.
+    let skew_factor = F4 * x + F4 * y + F4 * z + F4 * w; // Multiplying before adding keep values smaller, adding precision but is slower.
 
-    // Skew the (x,y,z,w) space to determine which cell of 24 simplices we're in
-    let mut i = (position.x + skew_factor).floor() as i64;
-    let mut j = (position.y + skew_factor).floor() as i64; // TODO fast floor function
-    let mut k = (position.z + skew_factor).floor() as i64;
-    let mut l = (position.w + skew_factor).floor() as i64;
+    let i = (x + skew_factor).floor();
+    let j = (y + skew_factor).floor(); // TODO fast floor function
+    let k = (z + skew_factor).floor();
+    let l = (w + skew_factor).floor();
 
     //Factor for 4D unskewing
-    let unskew_factor = G4 * i as f64 + G4 * j as f64 + G4 * k as f64 + G4 * l as f64; // Multiplying before adding prevent overflow but is slower.
+    let unskew_factor = G4 * i + G4 * j + G4 * k + G4 * l; // Multiplying before adding prevent overflow but is slower.
 
     //Unskew the cell origin back to (x,y,z,w) space
-    let x_0 = i as f64 - unskew_factor;
-    let y_0 = j as f64 - unskew_factor;
-    let z_0 = k as f64 - unskew_factor;
-    let w_0 = l as f64 - unskew_factor;
+    let x_0 = i - unskew_factor;
+    let y_0 = j - unskew_factor;
+    let z_0 = k - unskew_factor;
+    let w_0 = l - unskew_factor;
 
     //The x,y,z,w distances from the cell origin
-    offsets[0] = Vector4::new(
-        position.x - x_0,
-        position.y - y_0,
-        position.z - z_0,
-        position.w - w_0,
-    );
+    ([i, j, k, l], [x - x_0, y - y_0, z - z_0, w - w_0])
+}
+
+/// Offsets to the five corners of the simplex and their gradients.
+#[inline(always)]
+pub(crate) fn corners_4d(
+    cell: [i64; 4],
+    offset: [f64; 4],
+    table: &PermutationTable,
+) -> ([[f64; 4]; 5], [[f64; 4]; 5]) {
+    let [x, y, z, w] = offset;
 
     // For the 4D case, the simplex is a 4D shape I won't even try to describe.
     // To find out which of the 24 possible simplices we're in, we need to
@@ -62,32 +206,32 @@ pub fn with_derivatives_4d(position: &Point4<f64>, seed: &[u8; 512]) -> (f64, Ve
     let mut rankz = 0;
     let mut rankw = 0;
 
-    if offsets[0].x > offsets[0].y {
+    if x > y {
         rankx += 1;
     } else {
         ranky += 1;
     }
-    if offsets[0].x > offsets[0].z {
+    if x > z {
         rankx += 1;
     } else {
         rankz += 1;
     }
-    if offsets[0].x > offsets[0].w {
+    if x > w {
         rankx += 1;
     } else {
         rankw += 1;
     }
-    if offsets[0].y > offsets[0].z {
+    if y > z {
         ranky += 1;
     } else {
         rankz += 1;
     }
-    if offsets[0].y > offsets[0].w {
+    if y > w {
         ranky += 1;
     } else {
         rankw += 1;
     }
-    if offsets[0].z > offsets[0].w {
+    if z > w {
         rankz += 1;
     } else {
         rankw += 1;
@@ -115,110 +259,250 @@ pub fn with_derivatives_4d(position: &Point4<f64>, seed: &[u8; 512]) -> (f64, Ve
     let l3 = if rankw >= 1 { 1 } else { 0 };
     // The fifth corner has all coordinate offsets = 1, so no need to compute that.
 
-    // Offsets for second corner in (x,y,z,w) coords
-    offsets[1] = Vector4::new(
-        offsets[0].x - i1 as f64 + G4,
-        offsets[0].y - j1 as f64 + G4,
-        offsets[0].z - k1 as f64 + G4,
-        offsets[0].w - l1 as f64 + G4,
-    );
-
-    // Offsets for third corner in (x,y,z,w) coords
-    offsets[2] = Vector4::new(
-        offsets[0].x - i2 as f64 + 2.0 * G4,
-        offsets[0].y - j2 as f64 + 2.0 * G4,
-        offsets[0].z - k2 as f64 + 2.0 * G4,
-        offsets[0].w - l2 as f64 + 2.0 * G4,
-    );
-
-    // Offsets for fourth corner in (x,y,z,w) coords
-    offsets[3] = Vector4::new(
-        offsets[0].x - i3 as f64 + 3.0 * G4,
-        offsets[0].y - j3 as f64 + 3.0 * G4,
-        offsets[0].z - k3 as f64 + 3.0 * G4,
-        offsets[0].w - l3 as f64 + 3.0 * G4,
-    );
-
-    // Offsets for last corner in (x,y,z,w) coords
-    offsets[4] = Vector4::new(
-        offsets[0].x - 1.0 + 4.0 * G4,
-        offsets[0].y - 1.0 + 4.0 * G4,
-        offsets[0].z - 1.0 + 4.0 * G4,
-        offsets[0].w - 1.0 + 4.0 * G4,
-    );
+    let offsets = [
+        offset,
+        // Offsets for second corner in (x,y,z,w) coords
+        [
+            x - i1 as f64 + G4,
+            y - j1 as f64 + G4,
+            z - k1 as f64 + G4,
+            w - l1 as f64 + G4,
+        ],
+        // Offsets for third corner in (x,y,z,w) coords
+        [
+            x - i2 as f64 + 2.0 * G4,
+            y - j2 as f64 + 2.0 * G4,
+            z - k2 as f64 + 2.0 * G4,
+            w - l2 as f64 + 2.0 * G4,
+        ],
+        // Offsets for fourth corner in (x,y,z,w) coords
+        [
+            x - i3 as f64 + 3.0 * G4,
+            y - j3 as f64 + 3.0 * G4,
+            z - k3 as f64 + 3.0 * G4,
+            w - l3 as f64 + 3.0 * G4,
+        ],
+        // Offsets for last corner in (x,y,z,w) coords
+        [
+            x - 1.0 + 4.0 * G4,
+            y - 1.0 + 4.0 * G4,
+            z - 1.0 + 4.0 * G4,
+            w - 1.0 + 4.0 * G4,
+        ],
+    ];
 
     // Work out the hashed gradient indices of the five simplex corners
-    i &= 0xFF;
-    j &= 0xFF;
-    k &= 0xFF;
-    l &= 0xFF;
+    let i = cell[0] & 0xFF;
+    let j = cell[1] & 0xFF;
+    let k = cell[2] & 0xFF;
+    let l = cell[3] & 0xFF;
 
     let indices_i = [i, i + i1, i + i2, i + i3, i + 1];
     let indices_j = [j, j + j1, j + j2, j + j3, j + 1];
     let indices_k = [k, k + k1, k + k2, k + k3, k + 1];
     let indices_l = [l, l + l1, l + l2, l + l3, l + 1];
 
+    let mut gradients = [[0.0; 4]; 5];
+
+    for (c, gradient) in gradients.iter_mut().enumerate() {
+        *gradient = GRADIANTS_4D[(table[indices_i[c] as usize
+            + table[indices_j[c] as usize
+                + table[indices_k[c] as usize + table[indices_l[c] as usize] as usize] as usize]
+                as usize]
+            & 0x1F) as usize];
+    }
+
+    (offsets, gradients)
+}
+
+/// Contribution of one corner and its derivatives, zero outside the corner radius.
+#[inline(always)]
+pub(crate) fn falloff_4d(offset: &[f64; 4], gradient: &[f64; 4]) -> (f64, [f64; 4]) {
+    let [x, y, z, w] = *offset;
+
+    // Clamping instead of branching keeps the loop vectorizable
+    let t = (0.5 - (x * x + y * y + z * z + w * w)).max(0.0);
+
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let t4 = t2 * t2;
+
+    let grad_dot = gradient[0] * x + gradient[1] * y + gradient[2] * z + gradient[3] * w;
+
+    let slope = -8.0 * t3 * grad_dot;
+
+    (
+        t4 * grad_dot,
+        [
+            slope * x + t4 * gradient[0],
+            slope * y + t4 * gradient[1],
+            slope * z + t4 * gradient[2],
+            slope * w + t4 * gradient[3],
+        ],
+    )
+}
+
+pub fn with_derivatives_3d(
+    position: &Point3<f64>,
+    table: &PermutationTable,
+) -> (f64, Vector3<f64>) {
+    let (cell, offset) = skew_3d(position.x, position.y, position.z);
+
+    let cell = [cell[0] as i64, cell[1] as i64, cell[2] as i64];
+
+    sum_corners_3d(cell, offset, table)
+}
+
+/// Same as with_derivatives_3d at origin + offset.
+/// The integer origin keeps the precision far away from zero, only the offset should stay small.
+/// Origin components must stay within +/- 2^61.
+pub fn with_derivatives_3d_from_origin(
+    origin: &Point3<i64>,
+    offset: &Vector3<f64>,
+    table: &PermutationTable,
+) -> (f64, Vector3<f64>) {
+    debug_assert!(
+        origin.iter().all(|c| c.unsigned_abs() <= 1 << 61),
+        "origin out of the +/- 2^61 range"
+    );
+
+    // Skewing the origin is split in a whole part and a fraction.
+    // F3 is 1/3 so both are exact.
+    let sum = origin.x + origin.y + origin.z;
+
+    let whole = sum.div_euclid(3);
+    let fraction = sum.rem_euclid(3) as f64 / 3.0;
+
+    let skew_factor = F3 * offset.x + F3 * offset.y + F3 * offset.z + fraction;
+
+    let i = (offset.x + skew_factor).floor();
+    let j = (offset.y + skew_factor).floor();
+    let k = (offset.z + skew_factor).floor();
+
+    // The unskewed whole part cancels out with the origin, only the fraction remains.
+    let unskew_factor = G3 * i + G3 * j + G3 * k + fraction * ORIGIN_UNSKEW_3D;
+
+    let cell = [
+        origin.x + whole + i as i64,
+        origin.y + whole + j as i64,
+        origin.z + whole + k as i64,
+    ];
+
+    let offset = [
+        offset.x - (i - unskew_factor),
+        offset.y - (j - unskew_factor),
+        offset.z - (k - unskew_factor),
+    ];
+
+    sum_corners_3d(cell, offset, table)
+}
+
+/// Same as with_derivatives_3d with the second derivatives added.
+pub fn with_hessian_3d(
+    position: &Point3<f64>,
+    table: &PermutationTable,
+) -> (f64, Vector3<f64>, Matrix3<f64>) {
+    let (cell, offset) = skew_3d(position.x, position.y, position.z);
+
+    let cell = [cell[0] as i64, cell[1] as i64, cell[2] as i64];
+
+    let (offsets, gradients) = corners_3d(cell, offset, table);
+
     let mut n = 0.0;
-    let mut derivatives = Vector4::zeros();
+    let mut derivatives = Vector3::zeros();
+    let mut hessian = Matrix3::zeros();
 
-    for (i, offset) in offsets.iter().enumerate() {
-        let t = 0.5 - offset.dot(&offset);
+    for (offset, gradient) in offsets.iter().zip(gradients.iter()) {
+        let offset = Vector3::from(*offset);
+        let gradient = Vector3::from(*gradient);
 
-        if t < 0.0 {
-            continue;
-        }
+        let t = (0.5 - offset.norm_squared()).max(0.0);
 
         let t2 = t * t;
+        let t3 = t2 * t;
         let t4 = t2 * t2;
 
-        let gradient = {
-            let grad = &GRADIANTS_4D[(seed[indices_i[i] as usize
-                + seed[indices_j[i] as usize
-                    + seed[indices_k[i] as usize] as usize
-                    + seed[indices_l[i] as usize] as usize] as usize]
-                & 0x1F) as usize];
-
-            Vector4::new(grad[0], grad[1], grad[2], grad[3])
-        };
-
         let grad_dot = gradient.dot(&offset);
 
         n += t4 * grad_dot;
+        derivatives += offset * (-8.0 * t3 * grad_dot) + gradient * t4;
 
-        derivatives += -8.0 * t2 * t * offset * grad_dot + t4 * gradient;
+        let cross = offset * gradient.transpose();
+
+        hessian += offset * offset.transpose() * (48.0 * t2 * grad_dot)
+            - (cross + cross.transpose()) * (8.0 * t3)
+            - Matrix3::identity() * (8.0 * t3 * grad_dot);
     }
 
-    (n * 62.0, derivatives * 62.0)
+    (
+        n * NORMALIZE_3D,
+        derivatives * NORMALIZE_3D,
+        hessian * NORMALIZE_3D,
+    )
 }
 
-pub fn with_derivatives_3d(position: &Point3<f64>, seed: &[u8; 512]) -> (f64, Vector3<f64>) {
-    let mut offsets = [Vector3::zeros(); 4];
+#[inline(always)]
+fn sum_corners_3d(
+    cell: [i64; 3],
+    offset: [f64; 3],
+    table: &PermutationTable,
+) -> (f64, Vector3<f64>) {
+    let (offsets, gradients) = corners_3d(cell, offset, table);
 
-    let skew_factor = F3 * position.x + F3 * position.y + F3 * position.z; // Very nice and simple skew factor for 3D
+    let mut n = 0.0;
+    let mut derivatives = [0.0; 3];
 
-    // Skew the input space to determine which simplex cell we're in
-    let mut i = (position.x + skew_factor).floor() as i64;
-    let mut j = (position.y + skew_factor).floor() as i64;
-    let mut k = (position.z + skew_factor).floor() as i64;
+    for (offset, gradient) in offsets.iter().zip(gradients.iter()) {
+        let (value, deriv) = falloff_3d(offset, gradient);
+
+        n += value;
+
+        for (total, deriv) in derivatives.iter_mut().zip(deriv.iter()) {
+            *total += deriv;
+        }
+    }
+
+    (n * NORMALIZE_3D, Vector3::from(derivatives) * NORMALIZE_3D)
+}
+
+/// Skew the input space to determine which simplex cell we're in.
+/// Return the floored cell and the x,y,z distances from the cell origin.
+#[inline(always)]
+pub(crate) fn skew_3d(x: f64, y: f64, z: f64) -> ([f64; 3], [f64; 3]) {
+    let skew_factor = F3 * x + F3 * y + F3 * z; // Very nice and simple skew factor for 3D
+
+    let i = (x + skew_factor).floor();
+    let j = (y + skew_factor).floor();
+    let k = (z + skew_factor).floor();
 
     //Factor for 3D unskewing
-    let unskew_factor = G3 * i as f64 + G3 * j as f64 + G3 * k as f64;
+    let unskew_factor = G3 * i + G3 * j + G3 * k;
 
     //Unskew the cell origin back to (x,y,z) space
-    let x_0 = i as f64 - unskew_factor;
-    let y_0 = j as f64 - unskew_factor;
-    let z_0 = k as f64 - unskew_factor;
+    let x_0 = i - unskew_factor;
+    let y_0 = j - unskew_factor;
+    let z_0 = k - unskew_factor;
 
     //The x,y,z distances from the cell origin
-    offsets[0] = Vector3::new(position.x - x_0, position.y - y_0, position.z - z_0);
+    ([i, j, k], [x - x_0, y - y_0, z - z_0])
+}
+
+/// Offsets to the four corners of the simplex and their gradients.
+#[inline(always)]
+pub(crate) fn corners_3d(
+    cell: [i64; 3],
+    offset: [f64; 3],
+    table: &PermutationTable,
+) -> ([[f64; 3]; 4], [[f64; 3]; 4]) {
+    let [x, y, z] = offset;
 
     // For the 3D case, the simplex shape is a slightly irregular tetrahedron.
     // Determine which simplex we are in.
-    let (i1, j1, k1, i2, j2, k2) = if offsets[0].x >= offsets[0].y {
-        if offsets[0].y >= offsets[0].z {
+    let (i1, j1, k1, i2, j2, k2) = if x >= y {
+        if y >= z {
             // X Y Z order
             (1, 0, 0, 1, 1, 0)
-        } else if offsets[0].x >= offsets[0].z {
+        } else if x >= z {
             // X Z Y order
             (1, 0, 0, 1, 0, 1)
         } else {
@@ -227,10 +511,10 @@ pub fn with_derivatives_3d(position: &Point3<f64>, seed: &[u8; 512]) -> (f64, Ve
         }
     } else {
         // x0<y0
-        if offsets[0].y < offsets[0].z {
+        if y < z {
             // Z Y X order
             (0, 0, 1, 0, 1, 1)
-        } else if offsets[0].x < offsets[0].z {
+        } else if x < z {
             // Y Z X order
             (0, 1, 0, 0, 1, 1)
         } else {
@@ -239,41 +523,116 @@ pub fn with_derivatives_3d(position: &Point3<f64>, seed: &[u8; 512]) -> (f64, Ve
         }
     };
 
-    // Offsets for second corner in (x,y,z) coords
-    offsets[1] = Vector3::new(
-        offsets[0].x - i1 as f64 + G3,
-        offsets[0].y - j1 as f64 + G3,
-        offsets[0].z - k1 as f64 + G3,
-    );
+    let offsets = [
+        offset,
+        // Offsets for second corner in (x,y,z) coords
+        [x - i1 as f64 + G3, y - j1 as f64 + G3, z - k1 as f64 + G3],
+        // Offsets for third corner in (x,y,z) coords
+        [
+            x - i2 as f64 + 2.0 * G3,
+            y - j2 as f64 + 2.0 * G3,
+            z - k2 as f64 + 2.0 * G3,
+        ],
+        // Offsets for fourth corner in (x,y,z) coords
+        [x - 1.0 + 3.0 * G3, y - 1.0 + 3.0 * G3, z - 1.0 + 3.0 * G3],
+    ];
 
-    // Offsets for third corner in (x,y,z) coords
-    offsets[2] = Vector3::new(
-        offsets[0].x - i2 as f64 + 2.0 * G3,
-        offsets[0].y - j2 as f64 + 2.0 * G3,
-        offsets[0].z - k2 as f64 + 2.0 * G3,
-    );
+    // Work out the hashed gradient indices of the four simplex corners
+    let i = cell[0] & 0xFF;
+    let j = cell[1] & 0xFF;
+    let k = cell[2] & 0xFF;
 
-    // Offsets for fourth corner in (x,y,z) coords
-    offsets[3] = Vector3::new(
-        offsets[0].x - 1.0 + 3.0 * G3,
-        offsets[0].y - 1.0 + 3.0 * G3,
-        offsets[0].z - 1.0 + 3.0 * G3,
-    );
+    let indices_i = [i, i + i1, i + i2, i + 1];
+    let indices_j = [j, j + j1, j + j2, j + 1];
+    let indices_k = [k, k + k1, k + k2, k + 1];
 
-    // Work out the hashed gradient indices of the five simplex corners
+    let mut gradients = [[0.0; 3]; 4];
+
+    for (c, gradient) in gradients.iter_mut().enumerate() {
+        *gradient = GRADIANTS_3D[(table[indices_i[c] as usize
+            + table[indices_j[c] as usize + table[indices_k[c] as usize] as usize] as usize]
+            % 12) as usize];
+    }
+
+    (offsets, gradients)
+}
+
+/// Contribution of one corner and its derivatives, zero outside the corner radius.
+#[inline(always)]
+pub(crate) fn falloff_3d(offset: &[f64; 3], gradient: &[f64; 3]) -> (f64, [f64; 3]) {
+    let [x, y, z] = *offset;
+
+    // Clamping instead of branching keeps the loop vectorizable
+    let t = (0.5 - (x * x + y * y + z * z)).max(0.0);
+
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let t4 = t2 * t2;
+
+    let grad_dot = gradient[0] * x + gradient[1] * y + gradient[2] * z;
+
+    let slope = -8.0 * t3 * grad_dot;
+
+    (
+        t4 * grad_dot,
+        [
+            slope * x + t4 * gradient[0],
+            slope * y + t4 * gradient[1],
+            slope * z + t4 * gradient[2],
+        ],
+    )
+}
+
+pub fn with_derivatives_2d(
+    position: &Point2<f64>,
+    table: &PermutationTable,
+) -> (f64, Vector2<f64>) {
+    let mut offsets = [Vector2::zeros(); 3];
+
+    let skew_factor = F2 * position.x + F2 * position.y;
+
+    // Skew the input space to determine which simplex cell we're in
+    let mut i = (position.x + skew_factor).floor() as i64;
+    let mut j = (position.y + skew_factor).floor() as i64;
+
+    //Factor for 2D unskewing
+    let unskew_factor = G2 * i as f64 + G2 * j as f64;
+
+    //Unskew the cell origin back to (x,y) space
+    let x_0 = i as f64 - unskew_factor;
+    let y_0 = j as f64 - unskew_factor;
+
+    //The x,y distances from the cell origin
+    offsets[0] = Vector2::new(position.x - x_0, position.y - y_0);
+
+    // For the 2D case, the simplex shape is an equilateral triangle.
+    // Determine which simplex we are in.
+    let (i1, j1) = if offsets[0].x > offsets[0].y {
+        // lower triangle, XY order
+        (1, 0)
+    } else {
+        // upper triangle, YX order
+        (0, 1)
+    };
+
+    // Offsets for middle corner in (x,y) coords
+    offsets[1] = Vector2::new(offsets[0].x - i1 as f64 + G2, offsets[0].y - j1 as f64 + G2);
+
+    // Offsets for last corner in (x,y) coords
+    offsets[2] = Vector2::new(offsets[0].x - 1.0 + 2.0 * G2, offsets[0].y - 1.0 + 2.0 * G2);
+
+    // Work out the hashed gradient indices of the three simplex corners
     i &= 0xFF;
     j &= 0xFF;
-    k &= 0xFF;
 
-    let indices_i = [i, i + i1, i + i2, i + 1];
-    let indices_j = [j, j + j1, j + j2, j + 1];
-    let indices_k = [k, k + k1, k + k2, k + 1];
+    let indices_i = [i, i + i1, i + 1];
+    let indices_j = [j, j + j1, j + 1];
 
     let mut n = 0.0;
-    let mut derivatives = Vector3::zeros();
+    let mut derivatives = Vector2::zeros();
 
     for (i, offset) in offsets.iter().enumerate() {
-        let t = 0.5 - offset.dot(&offset);
+        let t = 0.5 - offset.dot(offset);
 
         if t < 0.0 {
             continue;
@@ -283,21 +642,174 @@ pub fn with_derivatives_3d(position: &Point3<f64>, seed: &[u8; 512]) -> (f64, Ve
         let t4 = t2 * t2;
 
         let gradient = {
-            let grad = &GRADIANTS_3D[(seed[indices_i[i] as usize
-                + seed[indices_j[i] as usize + seed[indices_k[i] as usize] as usize] as usize]
-                % 12) as usize];
+            let grad = &GRADIANTS_2D[(table
+                [indices_i[i] as usize + table[indices_j[i] as usize] as usize]
+                & 0x07) as usize];
 
-            Vector3::new(grad[0], grad[1], grad[2])
+            Vector2::new(grad[0], grad[1])
         };
 
-        let grad_dot = gradient.dot(&offset);
+        let grad_dot = gradient.dot(offset);
 
         n += t4 * grad_dot;
 
-        derivatives += -8.0 * t2 * offset * grad_dot + t4 * gradient;
+        derivatives += -8.0 * t2 * t * offset * grad_dot + t4 * gradient;
     }
 
-    (n * 72.0, derivatives * 72.0)
+    (n * NORMALIZE_2D, derivatives * NORMALIZE_2D)
+}
+
+/// 2D noise animated over time, sliced from 3D noise.
+/// Only the spatial derivatives are returned.
+pub fn with_derivatives_2d_over_time(
+    position: &Point2<f64>,
+    time: f64,
+    table: &PermutationTable,
+) -> (f64, Vector2<f64>) {
+    let space_time = Point3::new(position.x, position.y, time);
+
+    let (value, derivatives) = with_derivatives_3d(&space_time, table);
+
+    (value, Vector2::new(derivatives.x, derivatives.y))
+}
+
+/// Tileable noise repeating every period along each axis.
+/// Made by blending the noise of the neighbouring tiles,
+/// contrast is lower toward the middle of a tile.
+/// Panics if a period is not strictly positive and finite.
+pub fn periodic_3d(
+    position: &Point3<f64>,
+    period: &Vector3<f64>,
+    table: &PermutationTable,
+) -> (f64, Vector3<f64>) {
+    let tile = TileBlend::new(position.coords.as_slice(), period.as_slice());
+
+    let mut value = 0.0;
+    let mut derivatives = Vector3::zeros();
+
+    // One corner per neighbouring tile
+    for corner in 0..8 {
+        let (weight, weight_deriv) = tile.weight(corner);
+
+        let sample = Point3::from(Vector3::from_fn(|axis, _| tile.sample(corner, axis)));
+
+        let (noise, noise_deriv) = with_derivatives_3d(&sample, table);
+
+        value += weight * noise;
+        derivatives +=
+            Vector3::from_column_slice(&weight_deriv[..3]) * noise + noise_deriv * weight;
+    }
+
+    (value, derivatives)
+}
+
+/// Tileable noise repeating every period along each axis.
+/// Made by blending the noise of the neighbouring tiles,
+/// contrast is lower toward the middle of a tile.
+/// Panics if a period is not strictly positive and finite.
+pub fn periodic_4d(
+    position: &Point4<f64>,
+    period: &Vector4<f64>,
+    table: &PermutationTable,
+) -> (f64, Vector4<f64>) {
+    let tile = TileBlend::new(position.coords.as_slice(), period.as_slice());
+
+    let mut value = 0.0;
+    let mut derivatives = Vector4::zeros();
+
+    // One corner per neighbouring tile
+    for corner in 0..16 {
+        let (weight, weight_deriv) = tile.weight(corner);
+
+        let sample = Point4::from(Vector4::from_fn(|axis, _| tile.sample(corner, axis)));
+
+        let (noise, noise_deriv) = with_derivatives_4d(&sample, table);
+
+        value += weight * noise;
+        derivatives += Vector4::from(weight_deriv) * noise + noise_deriv * weight;
+    }
+
+    (value, derivatives)
+}
+
+/// Position inside a tile and smoothstep blending weights for each axis.
+struct TileBlend {
+    dimensions: usize,
+    local: [f64; 4],
+    period: [f64; 4],
+    blend: [f64; 4],
+    blend_deriv: [f64; 4],
+}
+
+impl TileBlend {
+    fn new(position: &[f64], period: &[f64]) -> Self {
+        let mut tile = Self {
+            dimensions: position.len(),
+            local: [0.0; 4],
+            period: [0.0; 4],
+            blend: [0.0; 4],
+            blend_deriv: [0.0; 4],
+        };
+
+        for axis in 0..tile.dimensions {
+            assert!(
+                period[axis] > 0.0 && period[axis].is_finite(),
+                "period must be strictly positive and finite, got {}",
+                period[axis]
+            );
+
+            let local = position[axis].rem_euclid(period[axis]);
+            let x = local / period[axis];
+
+            tile.local[axis] = local;
+            tile.period[axis] = period[axis];
+            tile.blend[axis] = x * x * (3.0 - 2.0 * x);
+            tile.blend_deriv[axis] = 6.0 * x * (1.0 - x) / period[axis];
+        }
+
+        tile
+    }
+
+    /// Bit N of corner is set when sampling the previous tile along axis N.
+    fn sample(&self, corner: usize, axis: usize) -> f64 {
+        if corner & (1 << axis) != 0 {
+            self.local[axis] - self.period[axis]
+        } else {
+            self.local[axis]
+        }
+    }
+
+    /// Weight of a corner and its derivatives.
+    fn weight(&self, corner: usize) -> (f64, [f64; 4]) {
+        let mut factors = [1.0; 4];
+        let mut factor_derivs = [0.0; 4];
+
+        for axis in 0..self.dimensions {
+            if corner & (1 << axis) != 0 {
+                factors[axis] = self.blend[axis];
+                factor_derivs[axis] = self.blend_deriv[axis];
+            } else {
+                factors[axis] = 1.0 - self.blend[axis];
+                factor_derivs[axis] = -self.blend_deriv[axis];
+            }
+        }
+
+        let weight = factors.iter().product();
+
+        let mut derivatives = [0.0; 4];
+
+        for (axis, deriv) in derivatives.iter_mut().enumerate().take(self.dimensions) {
+            *deriv = factor_derivs[axis];
+
+            for (other, factor) in factors.iter().enumerate() {
+                if other != axis {
+                    *deriv *= factor;
+                }
+            }
+        }
+
+        (weight, derivatives)
+    }
 }
 
 // Skewing and unskewing factors
@@ -305,9 +817,36 @@ const F4: f64 = 0.309_016_994_374_947_4; //(Math.Sqrt(5.0) - 1.0) / 4.0
 const G4: f64 = 0.138_196_601_125_010_5; //(5.0 - Math.Sqrt(5.0)) / 20.0
 const F3: f64 = 0.333_333_333_333_333_3; // 1.0 / 3.0
 const G3: f64 = 0.166_666_666_666_666_66; // 1.0 / 6.0
+const F2: f64 = 0.366_025_403_784_438_6; //(Math.Sqrt(3.0) - 1.0) / 2.0
+const G2: f64 = 0.211_324_865_405_187_1; //(3.0 - Math.Sqrt(3.0)) / 6.0
+
+// F4 in 128 bits fixed point, for skewing integer origins
+const F4_HIGH: u64 = 0x4f1b_bcdc_bfa5_3e0a;
+const F4_LOW: u64 = 0xf9ce_6030_2e76_e41a;
+const TWO_POW_64: f64 = 18_446_744_073_709_551_616.0;
+
+// Unskewing factors of the skewed origin fraction, 1.0 / Math.Sqrt(N + 1.0)
+const ORIGIN_UNSKEW_4D: f64 = 0.447_213_595_499_957_9;
+const ORIGIN_UNSKEW_3D: f64 = 0.5;
+
+// Scale the result to fit in [-1, 1]
+pub(crate) const NORMALIZE_4D: f64 = 62.0;
+pub(crate) const NORMALIZE_3D: f64 = 72.0;
+const NORMALIZE_2D: f64 = 70.0;
 
 //TODO use lazy static to generate gradients
 
+const GRADIANTS_2D: [[f64; 2]; 8] = [
+    [1.0, 1.0],
+    [-1.0, 1.0],
+    [1.0, -1.0],
+    [-1.0, -1.0],
+    [1.0, 0.0],
+    [-1.0, 0.0],
+    [0.0, 1.0],
+    [0.0, -1.0],
+];
+
 const GRADIANTS_3D: [[f64; 3]; 12] = [
     [1.0, 1.0, 0.0],
     [-1.0, 1.0, 0.0],
@@ -358,87 +897,306 @@ const GRADIANTS_4D: [[f64; 4]; 32] = [
     [-1.0, -1.0, -1.0, 0.0],
 ];
 
-pub const SEED1: [u8; 512] = [
-    210, 251, 147, 139, 214, 27, 149, 231, 162, 19, 136, 158, 232, 78, 82, 140, 37, 208, 50, 73,
-    79, 79, 240, 100, 144, 14, 172, 250, 59, 61, 226, 229, 69, 197, 143, 251, 125, 115, 197, 14,
-    102, 150, 63, 90, 157, 224, 161, 42, 42, 30, 183, 133, 168, 157, 150, 206, 221, 140, 70, 192,
-    153, 25, 7, 167, 9, 246, 218, 174, 99, 134, 163, 46, 38, 189, 228, 223, 54, 147, 16, 144, 213,
-    83, 59, 156, 31, 1, 80, 132, 0, 182, 205, 177, 79, 77, 230, 153, 109, 231, 185, 24, 253, 191,
-    193, 13, 2, 86, 95, 118, 181, 161, 179, 129, 203, 23, 170, 111, 174, 225, 188, 166, 123, 12,
-    163, 123, 206, 225, 80, 194, 191, 98, 248, 239, 155, 8, 102, 239, 133, 94, 194, 134, 42, 118,
-    102, 56, 28, 219, 202, 219, 150, 200, 3, 195, 36, 127, 57, 219, 179, 150, 75, 64, 148, 153,
-    126, 240, 121, 210, 216, 5, 149, 205, 10, 160, 247, 191, 137, 139, 210, 181, 189, 85, 237, 145,
-    75, 77, 97, 97, 181, 143, 93, 151, 166, 8, 176, 97, 182, 14, 126, 38, 187, 145, 23, 239, 64,
-    55, 203, 45, 25, 8, 237, 122, 43, 16, 17, 20, 216, 6, 31, 202, 232, 133, 163, 56, 210, 81, 169,
-    252, 245, 38, 160, 198, 172, 165, 234, 78, 77, 96, 32, 58, 126, 196, 117, 140, 247, 94, 203,
-    166, 232, 198, 143, 247, 126, 175, 42, 21, 185, 70, 210, 251, 147, 139, 214, 27, 149, 231, 162,
-    19, 136, 158, 232, 78, 82, 140, 37, 208, 50, 73, 79, 79, 240, 100, 144, 14, 172, 250, 59, 61,
-    226, 229, 69, 197, 143, 251, 125, 115, 197, 14, 102, 150, 63, 90, 157, 224, 161, 42, 42, 30,
-    183, 133, 168, 157, 150, 206, 221, 140, 70, 192, 153, 25, 7, 167, 9, 246, 218, 174, 99, 134,
-    163, 46, 38, 189, 228, 223, 54, 147, 16, 144, 213, 83, 59, 156, 31, 1, 80, 132, 0, 182, 205,
-    177, 79, 77, 230, 153, 109, 231, 185, 24, 253, 191, 193, 13, 2, 86, 95, 118, 181, 161, 179,
-    129, 203, 23, 170, 111, 174, 225, 188, 166, 123, 12, 163, 123, 206, 225, 80, 194, 191, 98, 248,
-    239, 155, 8, 102, 239, 133, 94, 194, 134, 42, 118, 102, 56, 28, 219, 202, 219, 150, 200, 3,
-    195, 36, 127, 57, 219, 179, 150, 75, 64, 148, 153, 126, 240, 121, 210, 216, 5, 149, 205, 10,
-    160, 247, 191, 137, 139, 210, 181, 189, 85, 237, 145, 75, 77, 97, 97, 181, 143, 93, 151, 166,
-    8, 176, 97, 182, 14, 126, 38, 187, 145, 23, 239, 64, 55, 203, 45, 25, 8, 237, 122, 43, 16, 17,
-    20, 216, 6, 31, 202, 232, 133, 163, 56, 210, 81, 169, 252, 245, 38, 160, 198, 172, 165, 234,
-    78, 77, 96, 32, 58, 126, 196, 117, 140, 247, 94, 203, 166, 232, 198, 143, 247, 126, 175, 42,
-    21, 185, 70,
-];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::test_utils::{check_derivatives, sample_positions};
 
-pub const SEED2: [u8; 512] = [
-    114, 89, 251, 181, 211, 196, 88, 90, 48, 82, 223, 224, 163, 161, 47, 17, 41, 25, 207, 28, 101,
-    194, 17, 131, 119, 6, 156, 185, 125, 216, 55, 103, 246, 133, 147, 142, 100, 102, 97, 144, 23,
-    182, 122, 119, 57, 255, 201, 37, 116, 223, 133, 58, 28, 173, 250, 31, 197, 139, 151, 179, 87,
-    137, 217, 229, 84, 235, 141, 93, 7, 9, 223, 147, 169, 70, 4, 135, 61, 120, 23, 56, 94, 209,
-    165, 254, 33, 185, 182, 235, 248, 228, 227, 249, 135, 235, 46, 17, 199, 221, 189, 198, 144, 57,
-    13, 2, 209, 43, 117, 210, 25, 246, 132, 60, 31, 6, 218, 68, 247, 44, 8, 29, 90, 254, 140, 227,
-    220, 66, 132, 83, 4, 232, 192, 24, 6, 11, 58, 175, 164, 78, 175, 236, 22, 216, 48, 25, 158,
-    227, 87, 36, 119, 62, 61, 236, 16, 9, 254, 26, 193, 141, 81, 167, 54, 183, 65, 133, 169, 20,
-    189, 15, 103, 43, 167, 219, 123, 146, 56, 117, 119, 85, 210, 241, 213, 140, 6, 158, 73, 152,
-    88, 227, 131, 49, 199, 110, 252, 203, 181, 17, 11, 180, 90, 68, 94, 110, 189, 46, 206, 181,
-    199, 216, 30, 47, 165, 185, 99, 213, 6, 133, 7, 25, 81, 154, 142, 37, 132, 83, 155, 183, 97,
-    223, 185, 126, 246, 104, 235, 234, 75, 162, 96, 233, 154, 124, 223, 184, 149, 48, 22, 110, 77,
-    237, 160, 110, 244, 11, 60, 233, 148, 212, 114, 89, 251, 181, 211, 196, 88, 90, 48, 82, 223,
-    224, 163, 161, 47, 17, 41, 25, 207, 28, 101, 194, 17, 131, 119, 6, 156, 185, 125, 216, 55, 103,
-    246, 133, 147, 142, 100, 102, 97, 144, 23, 182, 122, 119, 57, 255, 201, 37, 116, 223, 133, 58,
-    28, 173, 250, 31, 197, 139, 151, 179, 87, 137, 217, 229, 84, 235, 141, 93, 7, 9, 223, 147, 169,
-    70, 4, 135, 61, 120, 23, 56, 94, 209, 165, 254, 33, 185, 182, 235, 248, 228, 227, 249, 135,
-    235, 46, 17, 199, 221, 189, 198, 144, 57, 13, 2, 209, 43, 117, 210, 25, 246, 132, 60, 31, 6,
-    218, 68, 247, 44, 8, 29, 90, 254, 140, 227, 220, 66, 132, 83, 4, 232, 192, 24, 6, 11, 58, 175,
-    164, 78, 175, 236, 22, 216, 48, 25, 158, 227, 87, 36, 119, 62, 61, 236, 16, 9, 254, 26, 193,
-    141, 81, 167, 54, 183, 65, 133, 169, 20, 189, 15, 103, 43, 167, 219, 123, 146, 56, 117, 119,
-    85, 210, 241, 213, 140, 6, 158, 73, 152, 88, 227, 131, 49, 199, 110, 252, 203, 181, 17, 11,
-    180, 90, 68, 94, 110, 189, 46, 206, 181, 199, 216, 30, 47, 165, 185, 99, 213, 6, 133, 7, 25,
-    81, 154, 142, 37, 132, 83, 155, 183, 97, 223, 185, 126, 246, 104, 235, 234, 75, 162, 96, 233,
-    154, 124, 223, 184, 149, 48, 22, 110, 77, 237, 160, 110, 244, 11, 60, 233, 148, 212,
-];
+    const STEP: f64 = 1e-6;
+    const TOLERANCE: f64 = 1e-5;
 
-pub const SEED3: [u8; 512] = [
-    126, 95, 221, 58, 103, 82, 36, 217, 45, 94, 140, 103, 64, 98, 117, 106, 230, 247, 227, 164,
-    181, 110, 237, 212, 220, 104, 158, 104, 122, 76, 199, 217, 224, 207, 118, 237, 112, 234, 152,
-    32, 170, 115, 61, 193, 144, 95, 117, 125, 230, 231, 201, 39, 51, 15, 178, 89, 83, 143, 245, 23,
-    42, 141, 248, 40, 147, 138, 137, 181, 251, 196, 98, 227, 85, 148, 199, 2, 69, 86, 231, 198, 17,
-    129, 49, 242, 171, 245, 27, 0, 228, 106, 227, 235, 97, 58, 222, 159, 251, 79, 35, 136, 126, 42,
-    205, 173, 227, 54, 38, 205, 209, 131, 75, 58, 114, 3, 130, 198, 59, 201, 29, 143, 25, 3, 221,
-    237, 98, 80, 147, 50, 21, 127, 149, 139, 200, 121, 158, 83, 134, 198, 59, 183, 67, 211, 40, 63,
-    196, 101, 132, 81, 66, 50, 188, 46, 123, 126, 47, 162, 7, 226, 211, 154, 89, 17, 44, 140, 116,
-    243, 73, 196, 5, 49, 55, 110, 218, 95, 145, 59, 236, 130, 159, 14, 234, 232, 231, 113, 176,
-    145, 161, 223, 177, 116, 200, 0, 63, 206, 111, 223, 24, 229, 39, 36, 105, 77, 58, 254, 159,
-    207, 121, 187, 89, 143, 74, 199, 80, 235, 123, 51, 121, 185, 125, 119, 108, 97, 93, 242, 125,
-    232, 82, 76, 242, 32, 48, 63, 56, 24, 68, 205, 102, 223, 192, 114, 124, 74, 177, 14, 37, 7, 79,
-    53, 231, 5, 96, 186, 248, 148, 234, 52, 126, 95, 221, 58, 103, 82, 36, 217, 45, 94, 140, 103,
-    64, 98, 117, 106, 230, 247, 227, 164, 181, 110, 237, 212, 220, 104, 158, 104, 122, 76, 199,
-    217, 224, 207, 118, 237, 112, 234, 152, 32, 170, 115, 61, 193, 144, 95, 117, 125, 230, 231,
-    201, 39, 51, 15, 178, 89, 83, 143, 245, 23, 42, 141, 248, 40, 147, 138, 137, 181, 251, 196, 98,
-    227, 85, 148, 199, 2, 69, 86, 231, 198, 17, 129, 49, 242, 171, 245, 27, 0, 228, 106, 227, 235,
-    97, 58, 222, 159, 251, 79, 35, 136, 126, 42, 205, 173, 227, 54, 38, 205, 209, 131, 75, 58, 114,
-    3, 130, 198, 59, 201, 29, 143, 25, 3, 221, 237, 98, 80, 147, 50, 21, 127, 149, 139, 200, 121,
-    158, 83, 134, 198, 59, 183, 67, 211, 40, 63, 196, 101, 132, 81, 66, 50, 188, 46, 123, 126, 47,
-    162, 7, 226, 211, 154, 89, 17, 44, 140, 116, 243, 73, 196, 5, 49, 55, 110, 218, 95, 145, 59,
-    236, 130, 159, 14, 234, 232, 231, 113, 176, 145, 161, 223, 177, 116, 200, 0, 63, 206, 111, 223,
-    24, 229, 39, 36, 105, 77, 58, 254, 159, 207, 121, 187, 89, 143, 74, 199, 80, 235, 123, 51, 121,
-    185, 125, 119, 108, 97, 93, 242, 125, 232, 82, 76, 242, 32, 48, 63, 56, 24, 68, 205, 102, 223,
-    192, 114, 124, 74, 177, 14, 37, 7, 79, 53, 231, 5, 96, 186, 248, 148, 234, 52,
-];
+    #[test]
+    fn derivatives_2d() {
+        let table = PermutationTable::from_seed(1);
+
+        check_derivatives(TOLERANCE, |position| with_derivatives_2d(position, &table));
+    }
+
+    #[test]
+    fn derivatives_3d() {
+        let table = PermutationTable::from_seed(2);
+
+        check_derivatives(TOLERANCE, |position| with_derivatives_3d(position, &table));
+    }
+
+    #[test]
+    fn derivatives_4d() {
+        let table = PermutationTable::from_seed(3);
+
+        check_derivatives(TOLERANCE, |position| with_derivatives_4d(position, &table));
+    }
+
+    #[test]
+    fn lattice_wrap_4d() {
+        // Cells at 255 and -1 hash the largest table indices, the nested lookups must stay in range
+        for seed in 0..32 {
+            let table = PermutationTable::from_seed(seed);
+
+            for &base in &[255.0, -1.0] {
+                for index in 0..64 {
+                    let skewed = Vector4::new(
+                        base + (index & 3) as f64 * 0.3 + 0.05,
+                        base + ((index >> 2) & 3) as f64 * 0.3 + 0.05,
+                        base + (index >> 4) as f64 * 0.3 + 0.05,
+                        base + 0.55,
+                    );
+
+                    let position = Point4::from(skewed - Vector4::repeat(G4 * skewed.sum()));
+
+                    let (value, derivatives) = with_derivatives_4d(&position, &table);
+
+                    assert!(value.abs() <= 1.0);
+                    assert!(derivatives.iter().all(|d| d.is_finite()));
+                }
+            }
+
+            let origin = Point4::new(i64::MAX >> 5, i64::MIN >> 5, -1, 255);
+            let (value, _) = with_derivatives_4d_from_origin(&origin, &Vector4::zeros(), &table);
+            assert!(value.abs() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn range_2d() {
+        let table = PermutationTable::from_seed(4);
+
+        for x in 0..500 {
+            for y in 0..500 {
+                let position = Point2::new(x as f64 * 0.037, y as f64 * 0.041);
+
+                let (value, _) = with_derivatives_2d(&position, &table);
+
+                assert!(value.abs() <= 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn from_origin_3d() {
+        let table = PermutationTable::from_seed(5);
+
+        let origin = Point3::new(17, -4, 9);
+
+        for offset in sample_positions(100).map(|position| position.coords) {
+            let (value, derivatives) = with_derivatives_3d_from_origin(&origin, &offset, &table);
+
+            let position = Point3::new(17.0, -4.0, 9.0) + offset;
+            let (expected, expected_derivatives) = with_derivatives_3d(&position, &table);
+
+            assert!((value - expected).abs() < 1e-9);
+            assert!((derivatives - expected_derivatives).norm() < 1e-9);
+
+            // The noise repeats every 768 units along an axis
+            let far = origin + Vector3::new(768 * 1_000_000_000, 0, 0);
+
+            let (far_value, far_derivatives) =
+                with_derivatives_3d_from_origin(&far, &offset, &table);
+
+            assert_eq!(value, far_value);
+            assert_eq!(derivatives, far_derivatives);
+        }
+    }
+
+    #[test]
+    fn from_origin_3d_range_limit() {
+        let table = PermutationTable::from_seed(8);
+
+        // A multiple of the 768 period close to the documented range
+        let large = ((1 << 61) - 768) / 768 * 768;
+
+        let near = Point3::new(17, -4, 9);
+        let far = near + Vector3::new(large, -large, large);
+
+        for offset in sample_positions(100).map(|position| position.coords) {
+            let (value, derivatives) = with_derivatives_3d_from_origin(&near, &offset, &table);
+
+            let (far_value, far_derivatives) =
+                with_derivatives_3d_from_origin(&far, &offset, &table);
+
+            assert_eq!(value, far_value);
+            assert_eq!(derivatives, far_derivatives);
+        }
+    }
+
+    #[test]
+    fn from_origin_4d() {
+        let table = PermutationTable::from_seed(6);
+
+        let origin = Point4::new(-3, 12, 7, 250);
+
+        for offset in sample_positions(100).map(|position| position.coords) {
+            let (value, derivatives) = with_derivatives_4d_from_origin(&origin, &offset, &table);
+
+            let position = Point4::new(-3.0, 12.0, 7.0, 250.0) + offset;
+            let (expected, expected_derivatives) = with_derivatives_4d(&position, &table);
+
+            assert!((value - expected).abs() < 1e-9);
+            assert!((derivatives - expected_derivatives).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn from_origin_seamless() {
+        let table = PermutationTable::from_seed(7);
+
+        let origin = Point4::new(123_456_789_012, -98_765_432_109, 5_000_000_000, 42);
+        let offset = Vector4::new(0.25, 0.75, 0.5, 0.125);
+
+        let (value, derivatives) = with_derivatives_4d_from_origin(&origin, &offset, &table);
+
+        for axis in 0..4 {
+            let mut step = Point4::new(0, 0, 0, 0);
+            step[axis] = 1;
+
+            let mut moved_offset = offset;
+            moved_offset[axis] -= 1.0;
+
+            let (moved_value, moved_derivatives) =
+                with_derivatives_4d_from_origin(&(origin + step.coords), &moved_offset, &table);
+
+            assert!((value - moved_value).abs() < 1e-9);
+            assert!((derivatives - moved_derivatives).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn periodic() {
+        let table = PermutationTable::from_seed(8);
+
+        let period = Vector4::new(3.0, 5.5, 2.0, 10.0);
+
+        for position in sample_positions(100) {
+            let (value, derivatives) = periodic_4d(&position, &period, &table);
+
+            for axis in 0..4 {
+                let mut offset = Vector4::zeros();
+                offset[axis] = period[axis];
+
+                let (repeat, repeat_derivatives) =
+                    periodic_4d(&(position + offset * 3.0), &period, &table);
+
+                assert!((value - repeat).abs() < 1e-9);
+                assert!((derivatives - repeat_derivatives).norm() < 1e-9);
+
+                offset[axis] = STEP;
+
+                let (high, _) = periodic_4d(&(position + offset), &period, &table);
+                let (low, _) = periodic_4d(&(position - offset), &period, &table);
+
+                assert!((derivatives[axis] - (high - low) / (2.0 * STEP)).abs() < TOLERANCE);
+            }
+        }
+    }
+
+    #[test]
+    fn periodic_3d_seam() {
+        let table = PermutationTable::from_seed(9);
+
+        let period = Vector3::new(4.0, 2.5, 7.0);
+
+        for position in sample_positions(100) {
+            let (value, derivatives) = periodic_3d(&position, &period, &table);
+
+            for axis in 0..3 {
+                let mut offset = Vector3::zeros();
+                offset[axis] = period[axis];
+
+                let (repeat, repeat_derivatives) =
+                    periodic_3d(&(position - offset * 2.0), &period, &table);
+
+                assert!((value - repeat).abs() < 1e-9);
+                assert!((derivatives - repeat_derivatives).norm() < 1e-9);
+
+                offset[axis] = STEP;
+
+                let (high, _) = periodic_3d(&(position + offset), &period, &table);
+                let (low, _) = periodic_3d(&(position - offset), &period, &table);
+
+                assert!((derivatives[axis] - (high - low) / (2.0 * STEP)).abs() < TOLERANCE);
+
+                // Both sides of the tile border meet
+                let mut border = position;
+                border[axis] = period[axis];
+
+                let (after, _) = periodic_3d(&(border + offset), &period, &table);
+                let (before, _) = periodic_3d(&(border - offset), &period, &table);
+
+                assert!((after - before).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "period must be strictly positive")]
+    fn periodic_zero_period() {
+        let table = PermutationTable::from_seed(10);
+
+        periodic_3d(
+            &Point3::new(1.0, 2.0, 3.0),
+            &Vector3::new(1.0, 0.0, 1.0),
+            &table,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "period must be strictly positive")]
+    fn periodic_negative_period() {
+        let table = PermutationTable::from_seed(10);
+
+        periodic_4d(
+            &Point4::new(1.0, 2.0, 3.0, 4.0),
+            &Vector4::new(1.0, 1.0, -2.0, 1.0),
+            &table,
+        );
+    }
+
+    #[test]
+    fn hessian_3d() {
+        let table = PermutationTable::from_seed(4);
+
+        for position in sample_positions(100) {
+            let (value, derivatives, hessian) = with_hessian_3d(&position, &table);
+            let (expected_value, expected_derivatives) = with_derivatives_3d(&position, &table);
+
+            assert!((value - expected_value).abs() < 1e-12);
+            assert!((derivatives - expected_derivatives).norm() < 1e-12);
+
+            for axis in 0..3 {
+                let mut offset = Vector3::zeros();
+                offset[axis] = STEP;
+
+                let (_, high) = with_derivatives_3d(&(position + offset), &table);
+                let (_, low) = with_derivatives_3d(&(position - offset), &table);
+
+                let column = (high - low) / (2.0 * STEP);
+
+                assert!((hessian.column(axis) - column).norm() < TOLERANCE);
+            }
+        }
+    }
+
+    #[test]
+    fn hessian_4d() {
+        let table = PermutationTable::from_seed(9);
+
+        for position in sample_positions(100) {
+            let (value, derivatives, hessian) = with_hessian_4d(&position, &table);
+            let (expected_value, expected_derivatives) = with_derivatives_4d(&position, &table);
+
+            assert!((value - expected_value).abs() < 1e-12);
+            assert!((derivatives - expected_derivatives).norm() < 1e-12);
+
+            for axis in 0..4 {
+                let mut offset = Vector4::zeros();
+                offset[axis] = STEP;
+
+                let (_, high) = with_derivatives_4d(&(position + offset), &table);
+                let (_, low) = with_derivatives_4d(&(position - offset), &table);
+
+                let column = (high - low) / (2.0 * STEP);
+
+                assert!((hessian.column(axis) - column).norm() < TOLERANCE);
+            }
+        }
+    }
+}
@@ -1,11 +1,19 @@
 #![allow(dead_code)]
 
-use crate::noise::hash_fonctions;
-use nalgebra::{Point3, Vector3};
+use crate::noise::cellular;
+use nalgebra::{Point2, Point3, Vector2, Vector3};
 
 //https://www.iquilezles.org/www/articles/smoothvoronoi/smoothvoronoi.htm
 
-pub fn smooth_voronoi_3d(coordinates: Point3<f64>, scale: f64, hardness: f64) -> f64 {
+/// Smooth minimum of the distances to the feature points, higher hardness is closer to the true minimum.
+/// Derivatives are with respect to the unscaled coordinates.
+/// Only nearby cells are searched, a hardness below ~16 shows seams at cell borders.
+pub fn smooth_voronoi_3d(
+    coordinates: Point3<f64>,
+    scale: f64,
+    hardness: f64,
+    seed: u64,
+) -> (f64, Vector3<f64>) {
     let coordinates = coordinates * scale;
 
     let integral = Point3::new(
@@ -14,34 +22,136 @@ pub fn smooth_voronoi_3d(coordinates: Point3<f64>, scale: f64, hardness: f64) ->
         coordinates.z.floor(),
     );
 
-    let fract = Point3::new(
-        coordinates.x.fract(),
-        coordinates.y.fract(),
-        coordinates.z.fract(),
-    );
+    // fract() would be negative for negative coordinates
+    let fract = coordinates - integral;
+
+    let cell = [integral.x as i64, integral.y as i64, integral.z as i64];
 
-    let mut smooth_distance = 1.0;
+    // Starts empty, any extra term would pull the smooth minimum below the nearest distance
+    let mut smooth_distance = 0.0;
+    let mut derivatives = Vector3::zeros();
     for z in -2..3 {
         for y in -2..3 {
             for x in -2..3 {
-                let coords = Point3::new(
-                    integral.x + x as f64,
-                    integral.y + y as f64,
-                    integral.z + z as f64,
-                );
-
-                let result = Vector3::new(
-                    hash_fonctions::coordinates_hash_x(coords) - fract.x + x as f64,
-                    hash_fonctions::coordinates_hash_y(coords) - fract.y + y as f64,
-                    hash_fonctions::coordinates_hash_z(coords) - fract.z + z as f64,
-                );
+                let (i, j, k) = (cell[0] + x, cell[1] + y, cell[2] + z);
+
+                let result = cellular::feature_point_3d(i, j, k, seed) - fract
+                    + Vector3::new(x as f64, y as f64, z as f64);
 
                 let distance = result.dot(&result).sqrt();
 
-                smooth_distance += (-hardness * distance).exp()
+                let weight = (-hardness * distance).exp();
+
+                smooth_distance += weight;
+
+                // Moving the coordinates moves away from the feature point
+                if distance > 0.0 {
+                    derivatives -= result * (weight / distance);
+                }
             }
         }
     }
 
-    -(1.0 / hardness) * smooth_distance.ln()
+    (
+        -(1.0 / hardness) * smooth_distance.ln(),
+        derivatives * (scale / smooth_distance),
+    )
+}
+
+/// Smooth minimum of the distances to the feature points, higher hardness is closer to the true minimum.
+/// Derivatives are with respect to the unscaled coordinates.
+/// Only nearby cells are searched, a hardness below ~16 shows seams at cell borders.
+pub fn smooth_voronoi_2d(
+    coordinates: Point2<f64>,
+    scale: f64,
+    hardness: f64,
+    seed: u64,
+) -> (f64, Vector2<f64>) {
+    let coordinates = coordinates * scale;
+
+    let integral = Point2::new(coordinates.x.floor(), coordinates.y.floor());
+
+    let fract = coordinates - integral;
+
+    let cell = [integral.x as i64, integral.y as i64];
+
+    let mut smooth_distance = 0.0;
+    let mut derivatives = Vector2::zeros();
+    for y in -2..3 {
+        for x in -2..3 {
+            let (i, j) = (cell[0] + x, cell[1] + y);
+
+            let result =
+                cellular::feature_point_2d(i, j, seed) - fract + Vector2::new(x as f64, y as f64);
+
+            let distance = result.dot(&result).sqrt();
+
+            let weight = (-hardness * distance).exp();
+
+            smooth_distance += weight;
+
+            if distance > 0.0 {
+                derivatives -= result * (weight / distance);
+            }
+        }
+    }
+
+    (
+        -(1.0 / hardness) * smooth_distance.ln(),
+        derivatives * (scale / smooth_distance),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::test_utils::{check_derivatives, sample_positions};
+    use nalgebra::U3;
+
+    const TOLERANCE: f64 = 1e-4;
+
+    #[test]
+    fn derivatives_3d() {
+        check_derivatives(TOLERANCE, |position| {
+            smooth_voronoi_3d(*position, 0.7, 32.0, 3)
+        });
+    }
+
+    #[test]
+    fn derivatives_2d() {
+        check_derivatives(TOLERANCE, |position| {
+            smooth_voronoi_2d(*position, 0.7, 32.0, 3)
+        });
+    }
+
+    #[test]
+    fn close_to_cellular() {
+        for position in sample_positions(50).map(|position: Point3<f64>| position * 10.0) {
+            let (value, _) = smooth_voronoi_3d(position, 0.25, 64.0, 9);
+            let sample = cellular::cellular_3d(position, 0.25, 9);
+
+            // The smooth minimum is never above the true minimum
+            assert!(value <= sample.f1 + 1e-9);
+            assert!(sample.f1 - value < 0.1);
+        }
+    }
+
+    #[test]
+    fn pinned_values() {
+        // The smooth sum starts at zero, a start at one dragged every value toward zero
+        let expected = [
+            (0.606_704_953_798_287_4, 0.963_497_067_458_115_7),
+            (0.395_296_267_572_142_1, 0.937_558_315_920_936),
+            (0.261_187_461_624_362_24, 0.715_737_552_230_380_5),
+            (0.322_928_738_029_901_7, 0.472_815_344_207_221_6),
+        ];
+
+        for (position, &(expected_3d, expected_2d)) in sample_positions::<U3>(4).zip(&expected) {
+            let (value, _) = smooth_voronoi_3d(position, 0.7, 32.0, 3);
+            let (flat, _) = smooth_voronoi_2d(position.xy(), 0.7, 32.0, 3);
+
+            assert!((value - expected_3d).abs() < 1e-12);
+            assert!((flat - expected_2d).abs() < 1e-12);
+        }
+    }
 }
@@ -0,0 +1,242 @@
+#![allow(dead_code)]
+
+use nalgebra::{Point3, Point4, Vector3, Vector4};
+use rand::Rng;
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256StarStar;
+
+/// Measured distribution of a noise, used to check ranges and normalization constants.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statistics {
+    pub samples: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub variance: f64,
+    /// Evenly spaced bins from min to max.
+    pub histogram: Vec<u64>,
+    pub mean_slope: f64,
+    /// Largest gradient magnitude, useful to bound step sizes.
+    pub max_slope: f64,
+}
+
+impl Statistics {
+    /// None without values or bins, there is nothing to measure.
+    fn from_samples(values: &[f64], slopes: &[f64], bins: usize) -> Option<Self> {
+        let samples = values.len();
+
+        if samples == 0 || bins == 0 {
+            return None;
+        }
+
+        let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+        let mean = values.iter().sum::<f64>() / samples as f64;
+
+        let variance = values
+            .iter()
+            .map(|value| (value - mean) * (value - mean))
+            .sum::<f64>()
+            / samples as f64;
+
+        let mut histogram = vec![0; bins];
+
+        let width = (max - min) / bins as f64;
+
+        for value in values {
+            // The max lands in the last bin
+            let bin = if width > 0.0 {
+                (((value - min) / width) as usize).min(bins - 1)
+            } else {
+                0
+            };
+
+            histogram[bin] += 1;
+        }
+
+        Some(Self {
+            samples,
+            min,
+            max,
+            mean,
+            variance,
+            histogram,
+            mean_slope: slopes.iter().sum::<f64>() / samples as f64,
+            max_slope: slopes.iter().cloned().fold(0.0, f64::max),
+        })
+    }
+
+    pub fn standard_deviation(&self) -> f64 {
+        self.variance.sqrt()
+    }
+
+    /// Multiplier bringing the measured values in [-1, 1].
+    /// egg. 72.0 * suggested_normalization() for the 3D simplex constant.
+    /// None when every value was zero, no multiplier reaches 1.
+    pub fn suggested_normalization(&self) -> Option<f64> {
+        let peak = self.min.abs().max(self.max.abs());
+
+        if peak > 0.0 {
+            Some(1.0 / peak)
+        } else {
+            None
+        }
+    }
+
+    /// Scale and bias mapping the measured range to [low, high].
+    /// None for a constant noise, there is no range to stretch.
+    pub fn suggested_scale_bias(&self, low: f64, high: f64) -> Option<(f64, f64)> {
+        if self.max <= self.min {
+            return None;
+        }
+
+        let scale = (high - low) / (self.max - self.min);
+
+        Some((scale, low - self.min * scale))
+    }
+}
+
+/// Sample the noise at random positions in [-extent, extent] on every axis.
+/// None when samples or bins is zero or the extent is not a positive number.
+pub fn analyze_3d<F>(
+    mut noise: F,
+    samples: usize,
+    extent: f64,
+    bins: usize,
+    seed: u64,
+) -> Option<Statistics>
+where
+    F: FnMut(&Point3<f64>) -> (f64, Vector3<f64>),
+{
+    if !(extent > 0.0 && extent.is_finite()) {
+        return None;
+    }
+
+    let mut rng = Xoshiro256StarStar::seed_from_u64(seed);
+
+    let mut values = Vec::with_capacity(samples);
+    let mut slopes = Vec::with_capacity(samples);
+
+    for _ in 0..samples {
+        let position = Point3::new(
+            rng.gen_range(-extent, extent),
+            rng.gen_range(-extent, extent),
+            rng.gen_range(-extent, extent),
+        );
+
+        let (value, derivatives) = noise(&position);
+
+        values.push(value);
+        slopes.push(derivatives.norm());
+    }
+
+    Statistics::from_samples(&values, &slopes, bins)
+}
+
+/// Sample the noise at random positions in [-extent, extent] on every axis.
+/// None when samples or bins is zero or the extent is not a positive number.
+pub fn analyze_4d<F>(
+    mut noise: F,
+    samples: usize,
+    extent: f64,
+    bins: usize,
+    seed: u64,
+) -> Option<Statistics>
+where
+    F: FnMut(&Point4<f64>) -> (f64, Vector4<f64>),
+{
+    if !(extent > 0.0 && extent.is_finite()) {
+        return None;
+    }
+
+    let mut rng = Xoshiro256StarStar::seed_from_u64(seed);
+
+    let mut values = Vec::with_capacity(samples);
+    let mut slopes = Vec::with_capacity(samples);
+
+    for _ in 0..samples {
+        let position = Point4::new(
+            rng.gen_range(-extent, extent),
+            rng.gen_range(-extent, extent),
+            rng.gen_range(-extent, extent),
+            rng.gen_range(-extent, extent),
+        );
+
+        let (value, derivatives) = noise(&position);
+
+        values.push(value);
+        slopes.push(derivatives.norm());
+    }
+
+    Statistics::from_samples(&values, &slopes, bins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::permutation_table::PermutationTable;
+    use crate::noise::simplex;
+
+    #[test]
+    fn simplex_range() {
+        let table = PermutationTable::from_seed(5);
+
+        let stats_3d = analyze_3d(
+            |point| simplex::with_derivatives_3d(point, &table),
+            100_000,
+            100.0,
+            20,
+            1,
+        )
+        .unwrap();
+
+        let stats_4d = analyze_4d(
+            |point| simplex::with_derivatives_4d(point, &table),
+            100_000,
+            100.0,
+            20,
+            1,
+        )
+        .unwrap();
+
+        for stats in &[stats_3d, stats_4d] {
+            assert!(stats.min >= -1.0 && stats.max <= 1.0);
+            assert!(stats.mean.abs() < 0.01);
+            assert_eq!(stats.histogram.iter().sum::<u64>(), 100_000);
+
+            let (scale, bias) = stats.suggested_scale_bias(-100.0, 100.0).unwrap();
+
+            assert!((stats.min * scale + bias + 100.0).abs() < 1e-9);
+            assert!((stats.max * scale + bias - 100.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn invalid_inputs() {
+        let flat = |_: &Point3<f64>| (0.0, Vector3::zeros());
+
+        assert_eq!(analyze_3d(flat, 0, 1.0, 20, 1), None);
+        assert_eq!(analyze_3d(flat, 10, 1.0, 0, 1), None);
+        assert_eq!(analyze_3d(flat, 10, 0.0, 20, 1), None);
+        assert_eq!(analyze_3d(flat, 10, f64::NAN, 20, 1), None);
+
+        // A constant lands in the first bin
+        let stats = analyze_3d(flat, 10, 1.0, 20, 1).unwrap();
+
+        assert_eq!(stats.histogram[0], 10);
+    }
+
+    #[test]
+    fn constant_noise() {
+        let zero = analyze_3d(|_| (0.0, Vector3::zeros()), 10, 1.0, 20, 1).unwrap();
+
+        assert_eq!(zero.suggested_normalization(), None);
+        assert_eq!(zero.suggested_scale_bias(-1.0, 1.0), None);
+
+        let constant = analyze_4d(|_| (0.25, Vector4::zeros()), 10, 1.0, 20, 1).unwrap();
+
+        assert_eq!(constant.suggested_normalization(), Some(4.0));
+        assert_eq!(constant.suggested_scale_bias(-1.0, 1.0), None);
+    }
+}
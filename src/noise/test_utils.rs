@@ -0,0 +1,39 @@
+use nalgebra::allocator::Allocator;
+use nalgebra::{DefaultAllocator, DimName, Point, VectorN};
+
+/// Positions along the same line in every dimension, extra axes sit at 0.5 and 1.7.
+pub fn sample_positions<D>(count: usize) -> impl Iterator<Item = Point<f64, D>>
+where
+    D: DimName,
+    DefaultAllocator: Allocator<f64, D>,
+{
+    (0..count).map(|index| {
+        let line = [index as f64 * 0.173, index as f64 * -0.311, 0.5, 1.7];
+
+        Point::from(VectorN::<f64, D>::from_fn(|axis, _| line[axis]))
+    })
+}
+
+/// Compare the derivatives of the noise to central differences at 100 sample positions.
+pub fn check_derivatives<D, F>(tolerance: f64, noise: F)
+where
+    D: DimName,
+    DefaultAllocator: Allocator<f64, D>,
+    F: Fn(&Point<f64, D>) -> (f64, VectorN<f64, D>),
+{
+    let step = 1e-6;
+
+    for position in sample_positions::<D>(100) {
+        let (_, derivatives) = noise(&position);
+
+        for axis in 0..D::dim() {
+            let mut offset = VectorN::<f64, D>::zeros();
+            offset[axis] = step;
+
+            let (high, _) = noise(&(&position + &offset));
+            let (low, _) = noise(&(&position - &offset));
+
+            assert!((derivatives[axis] - (high - low) / (2.0 * step)).abs() < tolerance);
+        }
+    }
+}
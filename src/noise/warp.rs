@@ -0,0 +1,252 @@
+#![allow(dead_code)]
+
+use crate::noise::curl;
+use crate::noise::noise_fn::{Noise, NoiseFn};
+use crate::noise::permutation_table::PermutationTable;
+use crate::noise::simplex;
+use nalgebra::{Matrix3, Matrix4};
+use nalgebra::{Point3, Point4};
+use nalgebra::{Vector3, Vector4};
+
+//https://www.iquilezles.org/www/articles/warp/warp.htm
+
+/// Sample the source noise after moving the position by the displacement noises, one per axis.
+/// Derivatives are exact, the jacobian of every iteration is carried along.
+pub fn warp_3d<S, W>(
+    position: &Point3<f64>,
+    source: &S,
+    displacement: &[W; 3],
+    strength: f64,
+    iterations: u32,
+) -> (f64, Vector3<f64>)
+where
+    S: NoiseFn<Point3<f64>>,
+    W: NoiseFn<Point3<f64>>,
+{
+    let mut warped = *position;
+    let mut jacobian = Matrix3::identity();
+
+    for _ in 0..iterations {
+        let (value_x, deriv_x) = displacement[0].sample(&warped);
+        let (value_y, deriv_y) = displacement[1].sample(&warped);
+        let (value_z, deriv_z) = displacement[2].sample(&warped);
+
+        let warp_jacobian = Matrix3::from_rows(&[
+            deriv_x.transpose(),
+            deriv_y.transpose(),
+            deriv_z.transpose(),
+        ]);
+
+        warped += Vector3::new(value_x, value_y, value_z) * strength;
+        jacobian = (Matrix3::identity() + warp_jacobian * strength) * jacobian;
+    }
+
+    let (value, derivatives) = source.sample(&warped);
+
+    (value, jacobian.tr_mul(&derivatives))
+}
+
+/// Sample the source noise after moving the position by the displacement noises, one per axis.
+/// Derivatives are exact, the jacobian of every iteration is carried along.
+pub fn warp_4d<S, W>(
+    position: &Point4<f64>,
+    source: &S,
+    displacement: &[W; 4],
+    strength: f64,
+    iterations: u32,
+) -> (f64, Vector4<f64>)
+where
+    S: NoiseFn<Point4<f64>>,
+    W: NoiseFn<Point4<f64>>,
+{
+    let mut warped = *position;
+    let mut jacobian = Matrix4::identity();
+
+    for _ in 0..iterations {
+        let mut offset = Vector4::zeros();
+        let mut warp_jacobian = Matrix4::zeros();
+
+        for (axis, noise) in displacement.iter().enumerate() {
+            let (value, deriv) = noise.sample(&warped);
+
+            offset[axis] = value;
+            warp_jacobian.set_row(axis, &deriv.transpose());
+        }
+
+        warped += offset * strength;
+        jacobian = (Matrix4::identity() + warp_jacobian * strength) * jacobian;
+    }
+
+    let (value, derivatives) = source.sample(&warped);
+
+    (value, jacobian.tr_mul(&derivatives))
+}
+
+/// Sample the source noise after moving the position along the gradient of the simplex warp noise.
+/// Derivatives are exact, the hessian of the warp noise is carried along.
+pub fn gradient_warp_3d<S>(
+    position: &Point3<f64>,
+    source: &S,
+    warp: &PermutationTable,
+    strength: f64,
+    iterations: u32,
+) -> (f64, Vector3<f64>)
+where
+    S: NoiseFn<Point3<f64>>,
+{
+    let mut warped = *position;
+    let mut jacobian = Matrix3::identity();
+
+    for _ in 0..iterations {
+        let (_, deriv, hessian) = simplex::with_hessian_3d(&warped, warp);
+
+        warped += deriv * strength;
+        jacobian = (Matrix3::identity() + hessian * strength) * jacobian;
+    }
+
+    let (value, derivatives) = source.sample(&warped);
+
+    (value, jacobian.tr_mul(&derivatives))
+}
+
+/// Sample the source noise after moving the position along the curl noise flow.
+/// Derivatives are exact, the jacobian of the flow is carried along.
+pub fn curl_warp_3d<S>(
+    position: &Point3<f64>,
+    time: f64,
+    source: &S,
+    tables: &[PermutationTable; 3],
+    strength: f64,
+    iterations: u32,
+) -> (f64, Vector3<f64>)
+where
+    S: NoiseFn<Point3<f64>>,
+{
+    let mut warped = *position;
+    let mut jacobian = Matrix3::identity();
+
+    for _ in 0..iterations {
+        let (flow, flow_jacobian) = curl::curl_noise_3d_with_jacobian(&warped, time, tables);
+
+        warped += flow * strength;
+        jacobian = (Matrix3::identity() + flow_jacobian * strength) * jacobian;
+    }
+
+    let (value, derivatives) = source.sample(&warped);
+
+    (value, jacobian.tr_mul(&derivatives))
+}
+
+/// Warping as a noise, see warp_3d and warp_4d.
+/// The displacement is an array of one noise per axis, its length picks the sampled domain.
+pub struct Warp<S, D> {
+    source: S,
+    displacement: D,
+    strength: f64,
+    iterations: u32,
+}
+
+impl<S, D> Noise for Warp<S, D> {}
+
+impl<S, D> Warp<S, D> {
+    pub fn new(source: S, displacement: D, strength: f64, iterations: u32) -> Self {
+        Self {
+            source,
+            displacement,
+            strength,
+            iterations,
+        }
+    }
+}
+
+impl<S, W> NoiseFn<Point3<f64>> for Warp<S, [W; 3]>
+where
+    S: NoiseFn<Point3<f64>>,
+    W: NoiseFn<Point3<f64>>,
+{
+    fn sample(&self, point: &Point3<f64>) -> (f64, Vector3<f64>) {
+        warp_3d(
+            point,
+            &self.source,
+            &self.displacement,
+            self.strength,
+            self.iterations,
+        )
+    }
+}
+
+impl<S, W> NoiseFn<Point4<f64>> for Warp<S, [W; 4]>
+where
+    S: NoiseFn<Point4<f64>>,
+    W: NoiseFn<Point4<f64>>,
+{
+    fn sample(&self, point: &Point4<f64>) -> (f64, Vector4<f64>) {
+        warp_4d(
+            point,
+            &self.source,
+            &self.displacement,
+            self.strength,
+            self.iterations,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::noise::noise_fn::Simplex;
+    use crate::noise::test_utils::check_derivatives;
+
+    #[test]
+    fn warp_derivatives() {
+        let source = Simplex::new(PermutationTable::from_seed(1));
+        let displacement = [
+            Simplex::new(PermutationTable::from_seed(2)),
+            Simplex::new(PermutationTable::from_seed(3)),
+            Simplex::new(PermutationTable::from_seed(4)),
+        ];
+
+        let warp = Warp::new(source, displacement, 0.4, 3);
+
+        check_derivatives(1e-3, |position: &Point3<f64>| warp.sample(position));
+    }
+
+    #[test]
+    fn warp_4d_derivatives() {
+        let source = Simplex::new(PermutationTable::from_seed(5));
+        let displacement = [
+            Simplex::new(PermutationTable::from_seed(6)),
+            Simplex::new(PermutationTable::from_seed(7)),
+            Simplex::new(PermutationTable::from_seed(8)),
+            Simplex::new(PermutationTable::from_seed(9)),
+        ];
+
+        let warp = Warp::new(source, displacement, 0.3, 2);
+
+        check_derivatives(1e-3, |position: &Point4<f64>| warp.sample(position));
+    }
+
+    #[test]
+    fn gradient_warp_derivatives() {
+        let source = Simplex::new(PermutationTable::from_seed(10));
+        let warp = PermutationTable::from_seed(11);
+
+        check_derivatives(1e-3, |position| {
+            gradient_warp_3d(position, &source, &warp, 0.2, 3)
+        });
+    }
+
+    #[test]
+    fn curl_warp_derivatives() {
+        let source = Simplex::new(PermutationTable::from_seed(12));
+        let tables = [
+            PermutationTable::from_seed(13),
+            PermutationTable::from_seed(14),
+            PermutationTable::from_seed(15),
+        ];
+
+        check_derivatives(1e-3, |position| {
+            curl_warp_3d(position, 0.5, &source, &tables, 0.1, 3)
+        });
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use crate::noise::hash_fonctions::split_mix_64;
 use rand::Rng;
 use rand::SeedableRng;
 use rand_xoshiro::Xoshiro256StarStar;
@@ -18,46 +19,7 @@ pub fn try_generate(
     false_neg_rate: u32,
     efficiency: f64,
 ) -> Option<u32> {
-    let mut rng = {
-        //TODO find better way to merge the two 128bits into 256 for seeding
-        let asteroid_bytes = asteroid_id.to_be_bytes();
-        let player_bytes = player_id.to_be_bytes();
-
-        Xoshiro256StarStar::from_seed([
-            asteroid_bytes[0],
-            asteroid_bytes[1],
-            asteroid_bytes[2],
-            asteroid_bytes[3],
-            asteroid_bytes[4],
-            asteroid_bytes[5],
-            asteroid_bytes[6],
-            asteroid_bytes[7],
-            asteroid_bytes[8],
-            asteroid_bytes[9],
-            asteroid_bytes[10],
-            asteroid_bytes[11],
-            asteroid_bytes[12],
-            asteroid_bytes[13],
-            asteroid_bytes[14],
-            asteroid_bytes[15],
-            player_bytes[0],
-            player_bytes[1],
-            player_bytes[2],
-            player_bytes[3],
-            player_bytes[4],
-            player_bytes[5],
-            player_bytes[6],
-            player_bytes[7],
-            player_bytes[8],
-            player_bytes[9],
-            player_bytes[10],
-            player_bytes[11],
-            player_bytes[12],
-            player_bytes[13],
-            player_bytes[14],
-            player_bytes[15],
-        ])
-    };
+    let mut rng = rng_from_ids(player_id, asteroid_id);
 
     if tier < get_tier(sample) {
         return None;
@@ -76,6 +38,33 @@ pub fn try_generate(
     Some((quantity as f64 * (1.0 + (efficiency / 100.0))) as u32)
 }
 
+/// Every state word depends on all of both IDs so players don't share rolls on the same asteroids.
+/// Each word mixes the xor of the three other halves, a reversible step, no two pairs of IDs share a seed.
+fn rng_from_ids(player_id: u128, asteroid_id: u128) -> Xoshiro256StarStar {
+    let halves = [
+        split_mix_64(asteroid_id as u64),
+        split_mix_64((asteroid_id >> 64) as u64 ^ 0x9e37_79b9_7f4a_7c15),
+        split_mix_64(player_id as u64 ^ 0xd1b5_4a32_d192_ed03),
+        split_mix_64((player_id >> 64) as u64 ^ 0x8cb9_2ba7_2f3d_8dd7),
+    ];
+
+    let all = halves[0] ^ halves[1] ^ halves[2] ^ halves[3];
+
+    let mut state = [0u64; 4];
+
+    for (word, half) in state.iter_mut().zip(halves.iter()) {
+        *word = split_mix_64(all ^ half);
+    }
+
+    let mut seed = [0u8; 32];
+
+    for (bytes, word) in seed.chunks_mut(8).zip(state.iter()) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+
+    Xoshiro256StarStar::from_seed(seed)
+}
+
 const MAX_QUANTITY_EXP: f64 = 9.0;
 
 fn get_quantity_from_sample(sample: f64) -> u32 {
@@ -87,13 +76,7 @@ fn normalize_sample(mut sample: f64) -> f64 {
     sample += 100.0; // 0 @ +200
     sample /= 200.0; // 0 @ +1
 
-    if sample > 1.0 {
-        sample = 1.0;
-    } else if sample < 0.0 {
-        sample = 0.0;
-    }
-
-    sample
+    sample.clamp(0.0, 1.0)
 }
 
 pub fn get_tier(mut sample: f64) -> u8 {
@@ -108,6 +91,8 @@ pub fn get_tier(mut sample: f64) -> u8 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::world::asteroids::asteroid_id;
+    use nalgebra::Point2;
     use rand::SeedableRng;
 
     #[test]
@@ -135,4 +120,38 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn independent_players() {
+        // About 1 in 10 asteroids pass the quantity roll at this sample
+        let sample = -100.0 + 200.0 / MAX_QUANTITY_EXP;
+
+        let passes = |player_id: u128| -> Vec<bool> {
+            (0..2000)
+                .map(|index| {
+                    let asteroid_id = asteroid_id(7, Point2::new(3, -2), index);
+
+                    try_generate(sample, player_id, asteroid_id, 10, u32::MAX, 0.0).is_some()
+                })
+                .collect()
+        };
+
+        let first = passes(1);
+        let second = passes(2);
+
+        for player in &[&first, &second] {
+            let count = player.iter().filter(|pass| **pass).count();
+
+            assert!(count > 120 && count < 320);
+        }
+
+        // Around 1 in 100 for independent rolls
+        let both = first
+            .iter()
+            .zip(second.iter())
+            .filter(|(a, b)| **a && **b)
+            .count();
+
+        assert!(both < 60);
+    }
 }
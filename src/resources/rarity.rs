@@ -1,10 +1,46 @@
 #![allow(dead_code)]
 
+use crate::noise::permutation_table::PermutationTable;
 use crate::noise::simplex;
 use nalgebra::Point3;
 use nalgebra::{Point4, Vector3};
 
-//TODO use struct for seed data
+/// Parameters of the three noise layers blended by get_samples, x is the coarsest layer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RarityLayers {
+    pub scales: Vector3<f64>,
+    /// Change of each layer per time step
+    pub frequencies: Vector3<f64>,
+    pub amplitudes: Vector3<f64>,
+    pub exponents: Vector3<i32>,
+}
+
+impl Default for RarityLayers {
+    /// Each layer is 4 times finer, changes twice as fast and weights half as much as the previous one.
+    fn default() -> Self {
+        Self {
+            scales: Vector3::new(1.0, 4.0, 16.0),
+            frequencies: Vector3::new(0.01, 0.02, 0.04),
+            amplitudes: Vector3::new(1.0, 0.5, 0.25),
+            exponents: Vector3::new(1, 1, 1),
+        }
+    }
+}
+
+impl RarityLayers {
+    /// Same as get_samples with these layers.
+    pub fn sample(&self, position: &Point3<f64>, time: u64, table: &PermutationTable) -> f64 {
+        get_samples(
+            position,
+            time,
+            &self.scales,
+            &self.frequencies,
+            &self.amplitudes,
+            &self.exponents,
+            table,
+        )
+    }
+}
 
 pub fn get_samples(
     position: &Point3<f64>,
@@ -13,7 +49,7 @@ pub fn get_samples(
     frequencies: &Vector3<f64>,
     amplitudes: &Vector3<f64>,
     exponents: &Vector3<i32>,
-    seed: &[u8; 512],
+    table: &PermutationTable,
 ) -> f64 {
     let space_time_x = Point4::new(
         position.x * scales.x,
@@ -36,9 +72,9 @@ pub fn get_samples(
         time as f64 * frequencies.z,
     );
 
-    let (sample_x, _) = simplex::with_derivatives_4d(&space_time_x, seed);
-    let (sample_y, _) = simplex::with_derivatives_4d(&space_time_y, seed);
-    let (sample_z, _) = simplex::with_derivatives_4d(&space_time_z, seed);
+    let (sample_x, _) = simplex::with_derivatives_4d(&space_time_x, table);
+    let (sample_y, _) = simplex::with_derivatives_4d(&space_time_y, table);
+    let (sample_z, _) = simplex::with_derivatives_4d(&space_time_z, table);
 
     let samples = &Vector3::new(sample_x, sample_y, sample_z);
 
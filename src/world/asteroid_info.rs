@@ -0,0 +1,276 @@
+#![allow(dead_code)]
+
+use crate::noise::hash_fonctions::split_mix_64;
+use nalgebra::{Unit, Vector3};
+use rand::Rng;
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256StarStar;
+use std::f64::consts::PI;
+
+/*
+    Physical properties of an asteroid, only derived from its ID.
+    Values are drawn in a fixed order, changing it changes every asteroid of every world.
+    Only IEEE basic operations are used, libm functions like powf or cos can differ between platforms.
+*/
+
+/// Meters
+const MIN_RADIUS: f64 = 5.0;
+const MAX_RADIUS: f64 = 500.0;
+
+/// Slope of the size distribution, the number of asteroids bigger than R goes down as R^-2.5
+const SIZE_EXPONENT: f64 = 2.5;
+
+/// Proportion of dark carbonaceous asteroids, the rest are bright and stony.
+const CARBONACEOUS_RATIO: f64 = 0.75;
+
+/// Kilograms per cubic meter
+const CARBONACEOUS_DENSITY: f64 = 1400.0;
+const STONY_DENSITY: f64 = 2700.0;
+
+/// Hours, rubble piles faster than the spin barrier would fly apart.
+const MIN_SPIN_PERIOD: f64 = 2.2;
+const MAX_SPIN_PERIOD: f64 = 100.0;
+
+/// Log2 of MAX_SPIN_PERIOD / MIN_SPIN_PERIOD, written out to not depend on log2.
+const SPIN_OCTAVES: f64 = 5.506_352_666_024_79;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AsteroidInfo {
+    /// Meters
+    pub radius: f64,
+    /// Kilograms
+    pub mass: f64,
+    /// Fraction of light reflected
+    pub albedo: f64,
+    pub spin_axis: Unit<Vector3<f64>>,
+    /// Radians per second
+    pub spin_rate: f64,
+    /// Seed of the procedural mesh
+    pub shape_seed: u64,
+}
+
+impl AsteroidInfo {
+    pub fn from_id(asteroid_id: u128) -> Self {
+        let mut rng = rng_from_id(asteroid_id);
+
+        let radius = power_law_radius(&mut rng);
+
+        let (albedo, density) = if rng.gen_bool(CARBONACEOUS_RATIO) {
+            (rng.gen_range(0.03, 0.1), CARBONACEOUS_DENSITY)
+        } else {
+            (rng.gen_range(0.1, 0.3), STONY_DENSITY)
+        };
+
+        let mass = density * 4.0 / 3.0 * PI * radius * radius * radius;
+
+        let spin_axis = uniform_axis(&mut rng);
+
+        let period = log_uniform_period(&mut rng);
+        let spin_rate = 2.0 * PI / (period * 3600.0);
+
+        Self {
+            radius,
+            mass,
+            albedo,
+            spin_axis,
+            spin_rate,
+            shape_seed: rng.gen(),
+        }
+    }
+}
+
+/// Truncated power law, the number of asteroids bigger than R goes down as R^-SIZE_EXPONENT.
+fn power_law_radius(rng: &mut Xoshiro256StarStar) -> f64 {
+    debug_assert_eq!(SIZE_EXPONENT, 2.5);
+
+    let ratio = MIN_RADIUS / MAX_RADIUS;
+
+    // Inverse of the R^-2 law only needs a square root,
+    // keeping its samples with a chance of sqrt(MIN_RADIUS / R) turns it into the R^-2.5 law.
+    loop {
+        let radius = MIN_RADIUS / (1.0 - (1.0 - ratio * ratio) * rng.gen::<f64>()).sqrt();
+        let keep: f64 = rng.gen();
+
+        if keep * keep * radius < MIN_RADIUS {
+            break radius;
+        }
+    }
+}
+
+/// Uniform on the sphere.
+fn uniform_axis(rng: &mut Xoshiro256StarStar) -> Unit<Vector3<f64>> {
+    // Rejection sampling keeps the directions uniform without cos and sin
+    loop {
+        let direction: Vector3<f64> = Vector3::new(
+            rng.gen_range(-1.0, 1.0),
+            rng.gen_range(-1.0, 1.0),
+            rng.gen_range(-1.0, 1.0),
+        );
+
+        let length = direction.norm_squared();
+
+        if length > 1e-6 && length <= 1.0 {
+            break Unit::new_unchecked(direction / length.sqrt());
+        }
+    }
+}
+
+/// Log uniform from MIN_SPIN_PERIOD to MAX_SPIN_PERIOD.
+fn log_uniform_period(rng: &mut Xoshiro256StarStar) -> f64 {
+    // Octaves are picked by their share of the log range, the last one only partly fits
+    let octave = (rng.gen::<f64>() * SPIN_OCTAVES).floor();
+    let low = MIN_SPIN_PERIOD * (1u32 << octave as u32) as f64;
+    let high = (low * 2.0).min(MAX_SPIN_PERIOD);
+
+    // Uniform samples kept with a chance of low / period are log uniform
+    loop {
+        let period = rng.gen_range(low, high);
+        let keep: f64 = rng.gen();
+
+        if keep * period < low {
+            break period;
+        }
+    }
+}
+
+/// Each half of the ID is mixed on its own so that no two IDs share a seed.
+fn rng_from_id(asteroid_id: u128) -> Xoshiro256StarStar {
+    let low = asteroid_id as u64;
+    let high = (asteroid_id >> 64) as u64;
+
+    let state = [
+        split_mix_64(low),
+        split_mix_64(high ^ 0x9e37_79b9_7f4a_7c15),
+        split_mix_64(low ^ split_mix_64(high)),
+        split_mix_64(high ^ split_mix_64(low) ^ 0xbf58_476d_1ce4_e5b9),
+    ];
+
+    let mut seed = [0u8; 32];
+
+    for (bytes, word) in seed.chunks_mut(8).zip(state.iter()) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+
+    Xoshiro256StarStar::from_seed(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn physical_ranges() {
+        let mut small = 0;
+
+        for id in 0..1000u128 {
+            let info = AsteroidInfo::from_id(id << 64 | id);
+
+            assert_eq!(info, AsteroidInfo::from_id(id << 64 | id));
+
+            assert!(info.radius >= MIN_RADIUS && info.radius <= MAX_RADIUS);
+            assert!(info.albedo >= 0.03 && info.albedo < 0.3);
+            assert!(info.mass > 0.0);
+            assert!((info.spin_axis.norm() - 1.0).abs() < 1e-12);
+            assert!(info.spin_rate > 0.0);
+
+            if info.radius < 2.0 * MIN_RADIUS {
+                small += 1;
+            }
+        }
+
+        // Most asteroids are small
+        assert!(small > 700);
+    }
+
+    #[test]
+    fn distributions() {
+        assert!((SPIN_OCTAVES - (MAX_SPIN_PERIOD / MIN_SPIN_PERIOD).log2()).abs() < 1e-12);
+
+        let infos: Vec<AsteroidInfo> = (0..20_000u128).map(AsteroidInfo::from_id).collect();
+
+        // Share of asteroids bigger than twice the min radius is 2^-2.5
+        let big = infos
+            .iter()
+            .filter(|info| info.radius > 2.0 * MIN_RADIUS)
+            .count();
+
+        assert!((big as f64 / 20_000.0 - 0.177).abs() < 0.01);
+
+        // As many periods in every octave
+        let first_octave = infos
+            .iter()
+            .filter(|info| 2.0 * PI / (info.spin_rate * 3600.0) < 2.0 * MIN_SPIN_PERIOD)
+            .count();
+
+        assert!((first_octave as f64 / 20_000.0 - 1.0 / SPIN_OCTAVES).abs() < 0.01);
+
+        // Axes spread evenly on both sides
+        let up = infos.iter().filter(|info| info.spin_axis.z > 0.0).count();
+
+        assert!((up as f64 / 20_000.0 - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn platform_independent() {
+        // Every client must agree to the bit, any change here changes every asteroid
+        let expected: [(u128, [u64; 8]); 3] = [
+            (
+                0,
+                [
+                    0x4018_7f00_9089_07cd,
+                    0x4134_8d3c_10ea_e155,
+                    0x3fa9_ab77_6d69_6c48,
+                    0xbfc4_17a1_f697_cb03,
+                    0x3fd9_c7ef_8f9c_7448,
+                    0x3fec_dad8_f63a_3768,
+                    0x3f48_f33d_fdf1_83f8,
+                    0x866e_c727_9a02_1962,
+                ],
+            ),
+            (
+                1,
+                [
+                    0x402c_9691_7f9d_ebd4,
+                    0x417f_8031_8c65_e8b3,
+                    0x3fce_de1e_262a_aa93,
+                    0x3fef_ef5f_9df4_7000,
+                    0xbf86_39c6_48ca_bba6,
+                    0xbfb0_1040_7f81_180d,
+                    0x3f11_b624_4bb9_1b55,
+                    0x56a4_7f48_e281_d531,
+                ],
+            ),
+            (
+                0xdead_beef << 64 | 42,
+                [
+                    0x4018_4a1c_83b0_fbdc,
+                    0x4134_093b_4431_44e4,
+                    0x3fb1_7b81_286b_bbf4,
+                    0x3fd5_e4dc_5aa0_975d,
+                    0x3fe8_f0e2_9337_2211,
+                    0xbfe0_cbd3_12a1_4e01,
+                    0x3f2b_e292_e3b0_f6fc,
+                    0xb31d_a4e0_7701_9bd9,
+                ],
+            ),
+        ];
+
+        for (id, bits) in &expected {
+            let info = AsteroidInfo::from_id(*id);
+
+            assert_eq!(
+                [
+                    info.radius.to_bits(),
+                    info.mass.to_bits(),
+                    info.albedo.to_bits(),
+                    info.spin_axis.x.to_bits(),
+                    info.spin_axis.y.to_bits(),
+                    info.spin_axis.z.to_bits(),
+                    info.spin_rate.to_bits(),
+                    info.shape_seed,
+                ],
+                *bits
+            );
+        }
+    }
+}
@@ -1,23 +1,35 @@
 #![allow(dead_code)]
 
-use nalgebra::{Point2, Point3};
+use crate::noise::curl;
+use crate::noise::hash_fonctions;
+use crate::noise::permutation_table::PermutationTable;
+use crate::world::poisson_disk::PoissonDisk;
+use nalgebra::{Point2, Point3, Vector3};
 use rand::Rng;
 use rand::SeedableRng;
 use rand_xoshiro::Xoshiro256Plus;
+use std::collections::HashMap;
 
 const GRID_SIZE: u32 = 5000;
 const GRID_SCALE: f64 = 1.0 / GRID_SIZE as f64;
 
 const WORLD_HEIGHT: u32 = 5000;
 
+/// Farthest an asteroid can be pushed out of its grid cell by the repulsors, in local units.
+/// Repulsors are at most 1 cell away and a push leaves the asteroid at most 1.25 from the repulsor.
+const MAX_DISPLACEMENT: f64 = 2.25;
+
 /// Number of asteroids every cubic kilometer
 const ASTEROID_DENSITY: u32 = 50;
 
 pub const ASTEROID_COUNT: u32 = (ASTEROID_DENSITY as f64 * GRID_SIZE as f64 / 1000.0) as u32;
 
 /// Generate ASTEROID_COUNT coordinates local to the provided grid cell.
-fn random_coordinates() -> Vec<Point3<f32>> {
-    let mut rng = Xoshiro256Plus::from_entropy();
+fn random_coordinates(world_seed: u64, grid_cell: Point2<i16>) -> Vec<Point3<f64>> {
+    let mut rng = Xoshiro256Plus::seed_from_u64(cell_seed(world_seed, grid_cell));
+
+    // Jump ahead so that coordinates don't reuse the sequence of this cell repulsor.
+    rng.jump();
 
     let mut coords = Vec::with_capacity(ASTEROID_COUNT as usize);
 
@@ -28,6 +40,11 @@ fn random_coordinates() -> Vec<Point3<f32>> {
     coords
 }
 
+/// Merge the world seed and the grid cell coordinates into one seed.
+fn cell_seed(world_seed: u64, grid_cell: Point2<i16>) -> u64 {
+    hash_fonctions::hash_2d(grid_cell.x as i64, grid_cell.y as i64, world_seed)
+}
+
 pub fn grid_cell_from_position(global_position: Point3<f64>) -> Point2<i16> {
     let pos_x = global_position.x * GRID_SCALE;
     let pos_z = global_position.z * GRID_SCALE;
@@ -39,9 +56,10 @@ pub fn grid_cell_from_position(global_position: Point3<f64>) -> Point2<i16> {
     let int_z = (pos_z - fract_z) as i16;
 
     // To avoid weird artifact near 0 add minus or plus 1 to the coords
+    // Positions past the edge of the world land in the last cell
     Point2::new(
-        int_x + fract_x.signum() as i16,
-        int_z + fract_z.signum() as i16,
+        int_x.saturating_add(fract_x.signum() as i16),
+        int_z.saturating_add(fract_z.signum() as i16),
     )
 }
 
@@ -59,25 +77,41 @@ fn local_to_global(local_position: Point3<f64>, grid_cell: Point2<i16>) -> Point
     Point3::new(x, y, z)
 }
 
+fn global_to_local(global_position: Point3<f64>, grid_cell: Point2<i16>) -> Point3<f64> {
+    let x = global_position.x / (GRID_SIZE as f64 * (grid_cell.x as f64).signum())
+        - (grid_cell.x as f64).abs()
+        + 1.0;
+
+    let y = global_position.y / WORLD_HEIGHT as f64 + 0.5;
+
+    let z = global_position.z / (GRID_SIZE as f64 * (grid_cell.y as f64).signum())
+        - (grid_cell.y as f64).abs()
+        + 1.0;
+
+    Point3::new(x, y, z)
+}
+
 /// Return local coords of all repulsors around this cell
-fn repulsor_points(grid_cell: Point2<i16>) -> Vec<Point3<f64>> {
+fn repulsor_points(world_seed: u64, grid_cell: Point2<i16>) -> Vec<Point3<f64>> {
     let mut coords = Vec::with_capacity(9);
 
     for x in -1..2 {
         for y in -1..2 {
-            let mut rng = {
-                let bytes_x = (grid_cell.x + x).to_be_bytes();
-                let bytes_y = (grid_cell.y + y).to_be_bytes();
-                let seed = u64::from_be_bytes([
-                    bytes_x[0], bytes_x[1], 0, 0, 0, 0, bytes_y[0], bytes_y[1],
-                ]);
-                Xoshiro256Plus::seed_from_u64(seed)
-            };
-
-            coords.push(Point3::new(
-                rng.gen::<f64>() + x as f64,
-                rng.gen(),
-                rng.gen::<f64>() + y as f64,
+            let neighbour = Point2::new(offset_cell(grid_cell.x, x), offset_cell(grid_cell.y, y));
+
+            // No neighbour past the edge of the world
+            if (x != 0 && neighbour.x == grid_cell.x) || (y != 0 && neighbour.y == grid_cell.y) {
+                continue;
+            }
+
+            let mut rng = Xoshiro256Plus::seed_from_u64(cell_seed(world_seed, neighbour));
+
+            let repulsor = Point3::new(rng.gen(), rng.gen(), rng.gen());
+
+            // Negative cells are mirrored, going through global coords keeps neighbours in place
+            coords.push(global_to_local(
+                local_to_global(repulsor, neighbour),
+                grid_cell,
             ));
         }
     }
@@ -85,9 +119,7 @@ fn repulsor_points(grid_cell: Point2<i16>) -> Vec<Point3<f64>> {
     coords
 }
 
-fn away_from_repulsor(mut local_position: Point3<f64>, grid_cell: Point2<i16>) -> Point3<f64> {
-    let repulsors = repulsor_points(grid_cell);
-
+fn away_from_repulsor(mut local_position: Point3<f64>, repulsors: &[Point3<f64>]) -> Point3<f64> {
     for repulsor in repulsors.iter() {
         let mut dir_away = local_position - repulsor;
 
@@ -108,8 +140,452 @@ fn away_from_repulsor(mut local_position: Point3<f64>, grid_cell: Point2<i16>) -
     local_position
 }
 
-//TODO pub fn generate_asteroids(global_grid_cell: Point2<i16>) -> Vec<Point3<f64>> {}
+/// Tables of the curl noise potential fields, shared by every grid cell of a world.
+fn vortex_tables(world_seed: u64) -> [PermutationTable; 3] {
+    [
+        PermutationTable::from_seed(world_seed.wrapping_add(1)),
+        PermutationTable::from_seed(world_seed.wrapping_add(2)),
+        PermutationTable::from_seed(world_seed.wrapping_add(3)),
+    ]
+}
+
+/// Move the asteroid at most strength grid cells toward the center of the curl noise flow turn it is in.
+/// Asteroids swirling around a vortex gather in its core, never past it.
+/// Only depends on the global position so it's seamless across grid cells.
+fn toward_vortex(
+    global_position: Point3<f64>,
+    tables: &[PermutationTable; 3],
+    strength: f64,
+) -> Point3<f64> {
+    // Nothing moves, no need for the curl noise hessians
+    if strength == 0.0 {
+        return global_position;
+    }
+
+    let curvature = curl::streamline_curvature_3d(&(global_position * GRID_SCALE), 0.0, tables);
+
+    let bend = curvature.norm();
+
+    if bend == 0.0 {
+        return global_position;
+    }
+
+    // The center of the turn is one over the curvature away, in grid cells
+    let distance = strength.min(1.0 / bend);
+
+    global_position + curvature / bend * distance * GRID_SIZE as f64
+}
+
+/// Generate the global coords of all asteroids in this grid cell.
+/// The same world seed and grid cell always produce the same asteroids.
+/// Vortex strength is how far asteroids gather toward vortices, in grid cells.
+pub fn generate_asteroids(
+    world_seed: u64,
+    grid_cell: Point2<i16>,
+    vortex_strength: f64,
+) -> Vec<Point3<f64>> {
+    let repulsors = repulsor_points(world_seed, grid_cell);
+
+    let tables = vortex_tables(world_seed);
+
+    random_coordinates(world_seed, grid_cell)
+        .into_iter()
+        .map(|local_position| away_from_repulsor(local_position, &repulsors))
+        .map(|local_position| local_to_global(local_position, grid_cell))
+        .map(|global_position| toward_vortex(global_position, &tables, vortex_strength))
+        .collect()
+}
+
+/// Stable ID of the asteroid at this index of generate_asteroids.
+/// Bits from high to low: world seed (64), grid cell x (16), grid cell y (16), index (32).
+pub fn asteroid_id(world_seed: u64, grid_cell: Point2<i16>, index: u32) -> u128 {
+    (world_seed as u128) << 64
+        | (grid_cell.x as u16 as u128) << 48
+        | (grid_cell.y as u16 as u128) << 32
+        | index as u128
+}
+
+/// World seed, grid cell and index of an asteroid ID.
+pub fn decode_asteroid_id(asteroid_id: u128) -> (u64, Point2<i16>, u32) {
+    let world_seed = (asteroid_id >> 64) as u64;
+    let x = (asteroid_id >> 48) as u16 as i16;
+    let y = (asteroid_id >> 32) as u16 as i16;
+    let index = asteroid_id as u32;
+
+    (world_seed, Point2::new(x, y), index)
+}
+
+/// Same as generate_asteroids with the ID of every asteroid.
+pub fn generate_asteroids_with_ids(
+    world_seed: u64,
+    grid_cell: Point2<i16>,
+    vortex_strength: f64,
+) -> impl Iterator<Item = (u128, Point3<f64>)> {
+    generate_asteroids(world_seed, grid_cell, vortex_strength)
+        .into_iter()
+        .zip(0..)
+        .map(move |(asteroid, index)| (asteroid_id(world_seed, grid_cell, index), asteroid))
+}
+
+/// Lazily generate the ID and global coords of every asteroid within radius of this global position.
+/// Only grid cells close enough to hold one are generated, one at a time.
+pub fn asteroids_in_radius(
+    world_seed: u64,
+    position: Point3<f64>,
+    radius: f64,
+    vortex_strength: f64,
+) -> impl Iterator<Item = (u128, Point3<f64>)> {
+    // Repulsors and vortices can push asteroids out of their cell
+    let reach = radius + (MAX_DISPLACEMENT + vortex_strength.abs()) * GRID_SIZE as f64;
+
+    let min = grid_cell_from_position(position - Vector3::new(reach, 0.0, reach));
+    let max = grid_cell_from_position(position + Vector3::new(reach, 0.0, reach));
+
+    // Cells are ordered along each axis, 0 is skipped
+    let cells_x = (min.x..=max.x).filter(|x| *x != 0);
+    let cells_y = move || (min.y..=max.y).filter(|y| *y != 0);
+
+    cells_x
+        .flat_map(move |x| cells_y().map(move |y| Point2::new(x, y)))
+        .filter(move |grid_cell| {
+            // Skip the corners of the square too far from the sphere
+            let corner_a = local_to_global(Point3::new(0.0, 0.5, 0.0), *grid_cell);
+            let corner_b = local_to_global(Point3::new(1.0, 0.5, 1.0), *grid_cell);
+
+            let closest_x = position
+                .x
+                .clamp(corner_a.x.min(corner_b.x), corner_a.x.max(corner_b.x));
+            let closest_z = position
+                .z
+                .clamp(corner_a.z.min(corner_b.z), corner_a.z.max(corner_b.z));
+
+            (position.x - closest_x).powi(2) + (position.z - closest_z).powi(2) <= reach * reach
+        })
+        .flat_map(move |grid_cell| {
+            generate_asteroids_with_ids(world_seed, grid_cell, vortex_strength)
+        })
+        .filter(move |(_, asteroid)| (asteroid - position).norm_squared() <= radius * radius)
+}
+
+/// Global coords of blue noise asteroids in this grid cell, see PoissonDisk.
+/// Radii are in local units, 1.0 is the width of a grid cell.
+/// The density is sampled at global coordinates.
+/// Neighbouring cells don't overlap, a cell fills up to its border around the asteroids of its higher priority neighbours.
+/// Those neighbours depend on their own, a call usually samples around a dozen cells.
+pub fn blue_noise_asteroids<F>(
+    world_seed: u64,
+    grid_cell: Point2<i16>,
+    sampler: &PoissonDisk,
+    density: F,
+) -> Vec<Point3<f64>>
+where
+    F: Fn(&Point3<f64>) -> f64,
+{
+    let mut placed = HashMap::new();
+
+    place_blue_noise(world_seed, grid_cell, sampler, &density, &mut placed)
+}
+
+/// Asteroids of a cell depend on the asteroids of its higher priority neighbours.
+/// Priorities strictly increase along the recursion so it always ends, cells are only sampled once.
+fn place_blue_noise<F>(
+    world_seed: u64,
+    grid_cell: Point2<i16>,
+    sampler: &PoissonDisk,
+    density: &F,
+    placed: &mut HashMap<Point2<i16>, Vec<Point3<f64>>>,
+) -> Vec<Point3<f64>>
+where
+    F: Fn(&Point3<f64>) -> f64,
+{
+    if let Some(asteroids) = placed.get(&grid_cell) {
+        return asteroids.clone();
+    }
+
+    let priority = cell_priority(world_seed, grid_cell);
+
+    let mut fixed = Vec::new();
+
+    for x in -1..2 {
+        for y in -1..2 {
+            let neighbour = Point2::new(offset_cell(grid_cell.x, x), offset_cell(grid_cell.y, y));
+
+            if neighbour == grid_cell || cell_priority(world_seed, neighbour) < priority {
+                continue;
+            }
+
+            fixed.extend(
+                place_blue_noise(world_seed, neighbour, sampler, density, placed)
+                    .into_iter()
+                    .map(|asteroid| global_to_local(asteroid, grid_cell)),
+            );
+        }
+    }
+
+    let asteroids: Vec<Point3<f64>> = sampler
+        .sample_with_fixed(
+            Point3::origin(),
+            Point3::new(1.0, 1.0, 1.0),
+            cell_seed(world_seed, grid_cell),
+            |local_position| density(&local_to_global(*local_position, grid_cell)),
+            &fixed,
+        )
+        .into_iter()
+        .map(|local_position| local_to_global(local_position, grid_cell))
+        .collect();
+
+    placed.insert(grid_cell, asteroids.clone());
+
+    asteroids
+}
+
+/// Break ties between neighbouring cells, the cell coords make every priority unique.
+fn cell_priority(world_seed: u64, grid_cell: Point2<i16>) -> (u64, i16, i16) {
+    let mut rng = Xoshiro256Plus::seed_from_u64(cell_seed(world_seed, grid_cell));
+
+    // Jump twice to not reuse the sequences of the coordinates and repulsor
+    rng.jump();
+    rng.jump();
+
+    (rng.gen(), grid_cell.x, grid_cell.y)
+}
+
+/// Neighbouring cell coordinate, 0 is skipped.
+fn offset_cell(cell: i16, offset: i16) -> i16 {
+    let neighbour = cell.saturating_add(offset);
+
+    if neighbour == 0 {
+        neighbour + offset
+    } else {
+        neighbour
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::kd_tree::KdTree;
+
+    #[test]
+    fn deterministic_asteroids() {
+        let grid_cell = Point2::new(-3, 7);
+
+        let first = generate_asteroids(42, grid_cell, 0.2);
+        let second = generate_asteroids(42, grid_cell, 0.2);
+
+        assert_eq!(first.len(), ASTEROID_COUNT as usize);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn seeded_asteroids() {
+        let grid_cell = Point2::new(1, 1);
+
+        let first = generate_asteroids(1, grid_cell, 0.2);
+        let second = generate_asteroids(2, grid_cell, 0.2);
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn seamless_blue_noise() {
+        let sampler = PoissonDisk::new(0.12, 0.2).unwrap();
+        let density = |_: &Point3<f64>| 0.5;
+
+        let radius = |position: &Point3<f64>| sampler.radius(density(position)) * GRID_SIZE as f64;
+
+        // Cells -1 and 1 touch at x = 0, bands are as wide as the spacing of the points
+        let band = sampler.radius(0.5) * GRID_SIZE as f64;
+
+        let mut border = 0;
+        let mut inside = 0;
+
+        for world_seed in 0..10 {
+            // Both cells share their neighbours, sample them once
+            let mut placed = HashMap::new();
+
+            let left = place_blue_noise(
+                world_seed,
+                Point2::new(-1, 1),
+                &sampler,
+                &density,
+                &mut placed,
+            );
+            let right = place_blue_noise(
+                world_seed,
+                Point2::new(1, 1),
+                &sampler,
+                &density,
+                &mut placed,
+            );
+
+            for a in &left {
+                for b in &right {
+                    assert!((a - b).norm() >= (radius(a) + radius(b)) * 0.5);
+                }
+            }
+
+            for asteroid in left.iter().chain(&right) {
+                let x = asteroid.x.abs();
+
+                if x < band {
+                    border += 1;
+                } else if (x - GRID_SIZE as f64 * 0.5).abs() < band * 0.5 {
+                    inside += 1;
+                }
+            }
+        }
+
+        // The border band spans both cells, as much volume as the two inside bands together
+        let ratio = border as f64 / inside as f64;
+
+        assert!((0.9..1.1).contains(&ratio));
+    }
+
+    #[test]
+    fn streamed_asteroids() {
+        let position = Point3::new(800.0, 100.0, -1500.0);
+        let radius = 4000.0;
+
+        let streamed: Vec<(u128, Point3<f64>)> =
+            asteroids_in_radius(3, position, radius, 0.5).collect();
+
+        let mut expected = Vec::new();
+
+        for x in -6..6 {
+            for y in -6..6 {
+                if x == 0 || y == 0 {
+                    continue;
+                }
+
+                expected.extend(
+                    generate_asteroids_with_ids(3, Point2::new(x, y), 0.5)
+                        .filter(|(_, asteroid)| (asteroid - position).norm() <= radius),
+                );
+            }
+        }
+
+        assert!(!streamed.is_empty());
+        assert_eq!(streamed.len(), expected.len());
+        assert!(streamed.iter().all(|asteroid| expected.contains(asteroid)));
+    }
+
+    #[test]
+    fn vortex_displacement() {
+        let grid_cell = Point2::new(2, -5);
+
+        let still = generate_asteroids(9, grid_cell, 0.0);
+        let moved = generate_asteroids(9, grid_cell, 0.3);
 
-// Ideas: push asteroid based on a vector perpendicular to the flow of curl noise.
-// if i'm correct it should pull asteroid to the center of vortices and out of the way of more laminar flow.
-// To choose left or right, compute the derivative for an axis perpendicular to the vector.
+        assert_eq!(still.len(), moved.len());
+
+        for (still, moved) in still.iter().zip(moved.iter()) {
+            assert!((moved - still).norm() <= 0.3 * GRID_SIZE as f64 + 1e-6);
+        }
+
+        assert_ne!(still, moved);
+    }
+
+    #[test]
+    fn vortex_clustering() {
+        // Asteroids with another one closer than 250 meters, more once gathered in vortices
+        let crowding = |strength: f64| {
+            let mut asteroids = Vec::new();
+
+            for x in 1..5 {
+                for y in 1..5 {
+                    asteroids.extend(generate_asteroids(4, Point2::new(x, y), strength));
+                }
+            }
+
+            let tree = KdTree::new(&asteroids);
+
+            asteroids
+                .iter()
+                .filter(|asteroid| tree.in_sphere(asteroid, 250.0).len() > 1)
+                .count()
+        };
+
+        assert!(crowding(0.2) as f64 > crowding(0.0) as f64 * 1.3);
+    }
+
+    #[test]
+    fn asteroid_ids() {
+        let cases = [
+            (0, Point2::new(1, 1), 0),
+            (u64::MAX, Point2::new(-1, i16::MIN), u32::MAX),
+            (42, Point2::new(i16::MAX, -300), 17),
+        ];
+
+        for (world_seed, grid_cell, index) in cases.iter() {
+            let id = asteroid_id(*world_seed, *grid_cell, *index);
+
+            assert_eq!(decode_asteroid_id(id), (*world_seed, *grid_cell, *index));
+        }
+
+        let grid_cell = Point2::new(-4, 2);
+
+        let asteroids = generate_asteroids(5, grid_cell, 0.1);
+
+        for (id, asteroid) in generate_asteroids_with_ids(5, grid_cell, 0.1) {
+            let (world_seed, cell, index) = decode_asteroid_id(id);
+
+            assert_eq!((world_seed, cell), (5, grid_cell));
+            assert_eq!(asteroids[index as usize], asteroid);
+        }
+    }
+
+    #[test]
+    fn independent_cell_seeds() {
+        // A plain xor of the seed and the cell made these equal
+        assert_ne!(
+            cell_seed(1, Point2::new(3, 4)),
+            cell_seed(0, Point2::new(3, 5))
+        );
+
+        assert_ne!(
+            generate_asteroids(1, Point2::new(3, 4), 0.0),
+            generate_asteroids(0, Point2::new(3, 5), 0.0)
+        );
+    }
+
+    #[test]
+    fn edge_of_the_world() {
+        for grid_cell in &[
+            Point2::new(i16::MAX, 1),
+            Point2::new(i16::MIN, i16::MAX),
+            Point2::new(-1, 1),
+        ] {
+            let repulsors = repulsor_points(1, *grid_cell);
+
+            assert!(repulsors.len() >= 4);
+
+            // Every repulsor is within a cell of this one, including across 0
+            assert!(repulsors
+                .iter()
+                .all(|repulsor| (-1.0..2.0).contains(&repulsor.x)
+                    && (-1.0..2.0).contains(&repulsor.z)));
+
+            assert!(!generate_asteroids(1, *grid_cell, 0.0).is_empty());
+        }
+
+        let edge = i16::MAX as f64 * GRID_SIZE as f64;
+
+        assert_eq!(
+            grid_cell_from_position(Point3::new(1e9, 0.0, -1e9)),
+            Point2::new(i16::MAX, i16::MIN)
+        );
+
+        // Queries reaching past the edge are clamped to the last cells
+        assert!(
+            asteroids_in_radius(1, Point3::new(edge - 100.0, 0.0, 2500.0), 5000.0, 0.2)
+                .next()
+                .is_some()
+        );
+        assert!(
+            asteroids_in_radius(1, Point3::new(1e9, 0.0, -1e9), 5000.0, 0.2)
+                .next()
+                .is_none()
+        );
+    }
+}
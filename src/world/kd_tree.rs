@@ -0,0 +1,280 @@
+#![allow(dead_code)]
+
+use nalgebra::{Point3, Vector3};
+
+// Implicit k-d tree, each sub-slice stores its median point in the middle.
+// Nothing but the points and their indices is stored, building is one median selection per level.
+
+/// Spatial index of points, queries return indices into the slice the tree was built from.
+pub struct KdTree {
+    points: Vec<Point3<f64>>,
+    indices: Vec<usize>,
+}
+
+impl KdTree {
+    pub fn new(points: &[Point3<f64>]) -> Self {
+        let mut entries: Vec<(Point3<f64>, usize)> = points.iter().cloned().zip(0..).collect();
+
+        build(&mut entries, 0);
+
+        Self {
+            points: entries.iter().map(|entry| entry.0).collect(),
+            indices: entries.iter().map(|entry| entry.1).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Index and distance of the closest point.
+    pub fn nearest(&self, position: &Point3<f64>) -> Option<(usize, f64)> {
+        self.k_nearest(position, 1).pop()
+    }
+
+    /// Index and distance of the k closest points, closest first.
+    pub fn k_nearest(&self, position: &Point3<f64>, k: usize) -> Vec<(usize, f64)> {
+        let mut best = Vec::with_capacity(k + 1);
+
+        if k > 0 {
+            self.nearest_search(0, self.len(), 0, position, k, &mut best);
+        }
+
+        best.into_iter()
+            .map(|(distance_sqr, index)| (self.indices[index], distance_sqr.sqrt()))
+            .collect()
+    }
+
+    pub fn in_sphere(&self, center: &Point3<f64>, radius: f64) -> Vec<usize> {
+        let mut found = Vec::new();
+
+        self.range_search(
+            0,
+            self.len(),
+            0,
+            &|axis, split| {
+                (
+                    center[axis] - radius <= split,
+                    center[axis] + radius >= split,
+                )
+            },
+            &|point| (point - center).norm_squared() <= radius * radius,
+            &mut found,
+        );
+
+        found
+    }
+
+    /// Every point inside the box, bounds included.
+    pub fn in_aabb(&self, min: &Point3<f64>, max: &Point3<f64>) -> Vec<usize> {
+        let mut found = Vec::new();
+
+        self.range_search(
+            0,
+            self.len(),
+            0,
+            &|axis, split| (min[axis] <= split, max[axis] >= split),
+            &|point| (0..3).all(|axis| point[axis] >= min[axis] && point[axis] <= max[axis]),
+            &mut found,
+        );
+
+        found
+    }
+
+    /// Every point within range of the apex and less than half_angle radians away from the direction.
+    pub fn in_cone(
+        &self,
+        apex: &Point3<f64>,
+        direction: &Vector3<f64>,
+        half_angle: f64,
+        range: f64,
+    ) -> Vec<usize> {
+        let mut found = Vec::new();
+
+        let direction = direction.normalize();
+        let cos_angle = half_angle.cos();
+
+        // The cone is inside the sphere of its range, only that sphere is used to skip branches
+        self.range_search(
+            0,
+            self.len(),
+            0,
+            &|axis, split| (apex[axis] - range <= split, apex[axis] + range >= split),
+            &|point| {
+                let offset = point - apex;
+                let distance = offset.norm();
+
+                distance <= range
+                    && (distance == 0.0 || offset.dot(&direction) >= cos_angle * distance)
+            },
+            &mut found,
+        );
+
+        found
+    }
+
+    fn range_search(
+        &self,
+        start: usize,
+        end: usize,
+        depth: usize,
+        sides: &impl Fn(usize, f64) -> (bool, bool),
+        inside: &impl Fn(&Point3<f64>) -> bool,
+        found: &mut Vec<usize>,
+    ) {
+        if start >= end {
+            return;
+        }
+
+        let middle = (start + end) / 2;
+        let point = &self.points[middle];
+        let axis = depth % 3;
+
+        if inside(point) {
+            found.push(self.indices[middle]);
+        }
+
+        let (left, right) = sides(axis, point[axis]);
+
+        if left {
+            self.range_search(start, middle, depth + 1, sides, inside, found);
+        }
+
+        if right {
+            self.range_search(middle + 1, end, depth + 1, sides, inside, found);
+        }
+    }
+
+    /// Best holds the squared distance and tree index of the k closest points, sorted.
+    fn nearest_search(
+        &self,
+        start: usize,
+        end: usize,
+        depth: usize,
+        position: &Point3<f64>,
+        k: usize,
+        best: &mut Vec<(f64, usize)>,
+    ) {
+        if start >= end {
+            return;
+        }
+
+        let middle = (start + end) / 2;
+        let point = &self.points[middle];
+        let axis = depth % 3;
+
+        let distance_sqr = (point - position).norm_squared();
+
+        if best.len() < k || distance_sqr < best[best.len() - 1].0 {
+            let insert_at = best.partition_point(|entry| entry.0 <= distance_sqr);
+
+            best.insert(insert_at, (distance_sqr, middle));
+            best.truncate(k);
+        }
+
+        let plane = position[axis] - point[axis];
+
+        // Closest side first, it shrinks the search of the other side
+        let (near, far) = if plane <= 0.0 {
+            ((start, middle), (middle + 1, end))
+        } else {
+            ((middle + 1, end), (start, middle))
+        };
+
+        self.nearest_search(near.0, near.1, depth + 1, position, k, best);
+
+        if best.len() < k || plane * plane < best[best.len() - 1].0 {
+            self.nearest_search(far.0, far.1, depth + 1, position, k, best);
+        }
+    }
+}
+
+fn build(entries: &mut [(Point3<f64>, usize)], depth: usize) {
+    if entries.len() <= 1 {
+        return;
+    }
+
+    let axis = depth % 3;
+    let middle = entries.len() / 2;
+
+    entries.select_nth_unstable_by(middle, |a, b| a.0[axis].total_cmp(&b.0[axis]));
+
+    let (left, right) = entries.split_at_mut(middle);
+
+    build(left, depth + 1);
+    build(&mut right[1..], depth + 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use rand::SeedableRng;
+    use rand_xoshiro::Xoshiro256StarStar;
+
+    #[test]
+    fn same_as_brute_force() {
+        let mut rng = Xoshiro256StarStar::seed_from_u64(5);
+
+        let points: Vec<Point3<f64>> = (0..500)
+            .map(|_| Point3::new(rng.gen(), rng.gen(), rng.gen()))
+            .collect();
+
+        let tree = KdTree::new(&points);
+
+        for _ in 0..50 {
+            let position = Point3::new(rng.gen(), rng.gen(), rng.gen());
+
+            let mut by_distance: Vec<(usize, f64)> = points
+                .iter()
+                .enumerate()
+                .map(|(index, point)| (index, (point - position).norm()))
+                .collect();
+            by_distance.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+            assert_eq!(tree.nearest(&position), Some(by_distance[0]));
+            assert_eq!(tree.k_nearest(&position, 7), by_distance[..7].to_vec());
+
+            let mut sphere = tree.in_sphere(&position, 0.2);
+            sphere.sort_unstable();
+
+            let expected: Vec<usize> = (0..points.len())
+                .filter(|index| (points[*index] - position).norm() <= 0.2)
+                .collect();
+
+            assert_eq!(sphere, expected);
+
+            let max = position + Vector3::new(0.3, 0.1, 0.2);
+
+            let mut aabb = tree.in_aabb(&position, &max);
+            aabb.sort_unstable();
+
+            let expected: Vec<usize> = (0..points.len())
+                .filter(|index| {
+                    let point = points[*index];
+                    (0..3).all(|axis| point[axis] >= position[axis] && point[axis] <= max[axis])
+                })
+                .collect();
+
+            assert_eq!(aabb, expected);
+
+            let direction = Vector3::new(rng.gen_range(-1.0, 1.0), 1.0, 0.5);
+
+            let mut cone = tree.in_cone(&position, &direction, 0.4, 0.5);
+            cone.sort_unstable();
+
+            let expected: Vec<usize> = (0..points.len())
+                .filter(|index| {
+                    let offset = points[*index] - position;
+                    offset.norm() <= 0.5 && offset.angle(&direction) <= 0.4
+                })
+                .collect();
+
+            assert_eq!(cone, expected);
+        }
+    }
+}
@@ -1,2 +1,5 @@
+pub mod asteroid_info;
 pub mod asteroids;
 pub mod cosmic_wind;
+pub mod kd_tree;
+pub mod poisson_disk;
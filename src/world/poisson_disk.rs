@@ -0,0 +1,369 @@
+#![allow(dead_code)]
+
+use nalgebra::{Point3, Vector3};
+use rand::Rng;
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256StarStar;
+
+//https://www.cs.ubc.ca/~rbridson/docs/bridson-siggraph07-poissondisk.pdf
+
+#[derive(Debug, PartialEq)]
+pub enum PoissonError {
+    /// The min radius must be positive and both radii finite
+    InvalidRadius,
+}
+
+/// Blue noise points, no two points closer than the average of their radii.
+/// The radius goes from max_radius where the density is 0 to min_radius where it is 1.
+pub struct PoissonDisk {
+    min_radius: f64,
+    max_radius: f64,
+    attempts: u32,
+}
+
+impl PoissonDisk {
+    /// Default of 30 attempts per point.
+    pub fn new(min_radius: f64, max_radius: f64) -> Result<Self, PoissonError> {
+        if !(min_radius > 0.0 && min_radius.is_finite() && max_radius.is_finite()) {
+            return Err(PoissonError::InvalidRadius);
+        }
+
+        Ok(Self {
+            min_radius,
+            max_radius: max_radius.max(min_radius),
+            attempts: 30,
+        })
+    }
+
+    /// Candidates tried around a point before giving up on it. More is denser but slower.
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts;
+        self
+    }
+
+    pub fn max_radius(&self) -> f64 {
+        self.max_radius
+    }
+
+    /// Radius at this density, clamped to [0, 1]. A non-finite density counts as 0.
+    pub fn radius(&self, density: f64) -> f64 {
+        // NaN would go through the clamp and never conflict with any point
+        let density = if density.is_finite() {
+            density.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        self.max_radius + (self.min_radius - self.max_radius) * density
+    }
+
+    /// Fill the box from min to max. The same seed always produce the same points.
+    /// Memory grows with the volume divided by min_radius cubed.
+    /// An empty or non-finite box has no points.
+    pub fn sample<F>(
+        &self,
+        min: Point3<f64>,
+        max: Point3<f64>,
+        seed: u64,
+        density: F,
+    ) -> Vec<Point3<f64>>
+    where
+        F: Fn(&Point3<f64>) -> f64,
+    {
+        self.sample_with_fixed(min, max, seed, density, &[])
+    }
+
+    /// Same as sample around points already placed, usually outside the box.
+    /// New points keep their distance to the fixed points, which are not returned.
+    pub fn sample_with_fixed<F>(
+        &self,
+        min: Point3<f64>,
+        max: Point3<f64>,
+        seed: u64,
+        density: F,
+        fixed: &[Point3<f64>],
+    ) -> Vec<Point3<f64>>
+    where
+        F: Fn(&Point3<f64>) -> f64,
+    {
+        let size = max - min;
+
+        if !size.iter().all(|side| *side > 0.0 && side.is_finite()) {
+            return Vec::new();
+        }
+
+        let mut rng = Xoshiro256StarStar::seed_from_u64(seed);
+
+        // Any two points are at least min_radius apart so each grid cell holds one point at most
+        let cell_size = self.min_radius / 3.0f64.sqrt();
+        let dimensions = [
+            (size.x / cell_size).ceil() as i64,
+            (size.y / cell_size).ceil() as i64,
+            (size.z / cell_size).ceil() as i64,
+        ];
+
+        let grid_cell = |point: &Point3<f64>| {
+            let cell = (point - min) / cell_size;
+
+            [
+                (cell.x as i64).min(dimensions[0] - 1),
+                (cell.y as i64).min(dimensions[1] - 1),
+                (cell.z as i64).min(dimensions[2] - 1),
+            ]
+        };
+
+        let grid_index =
+            |[x, y, z]: [i64; 3]| (x + dimensions[0] * (y + dimensions[1] * z)) as usize;
+
+        // Only fixed points within the largest radius of the box can conflict
+        let fixed: Vec<(Point3<f64>, f64)> = fixed
+            .iter()
+            .filter(|point| {
+                (0..3).all(|axis| {
+                    point[axis] > min[axis] - self.max_radius
+                        && point[axis] < max[axis] + self.max_radius
+                })
+            })
+            .map(|point| (*point, self.radius(density(point))))
+            .collect();
+
+        let conflicts_fixed = |candidate: &Point3<f64>, radius: f64| {
+            fixed.iter().any(|(point, fixed_radius)| {
+                (point - candidate).norm() < (fixed_radius + radius) * 0.5
+            })
+        };
+
+        // Index of the point in each grid cell
+        let mut grid = vec![None; (dimensions[0] * dimensions[1] * dimensions[2]) as usize];
+        let mut points = Vec::new();
+        let mut radii = Vec::new();
+        let mut active = Vec::new();
+
+        // The box may be crowded by the fixed points, the first point gets as many attempts as any other
+        let first = (0..self.attempts.max(1)).find_map(|_| {
+            let first = min + size.component_mul(&Vector3::new(rng.gen(), rng.gen(), rng.gen()));
+            let radius = self.radius(density(&first));
+
+            if conflicts_fixed(&first, radius) {
+                None
+            } else {
+                Some((first, radius))
+            }
+        });
+
+        if let Some((first, radius)) = first {
+            grid[grid_index(grid_cell(&first))] = Some(0);
+            points.push(first);
+            radii.push(radius);
+            active.push(0);
+        }
+
+        while !active.is_empty() {
+            let active_index = rng.gen_range(0, active.len());
+            let index = active[active_index];
+
+            let mut found = false;
+
+            for _ in 0..self.attempts {
+                let candidate = points[index] + random_offset(&mut rng, radii[index]);
+
+                if (0..3).any(|axis| candidate[axis] < min[axis] || candidate[axis] >= max[axis]) {
+                    continue;
+                }
+
+                let radius = self.radius(density(&candidate));
+
+                let [x, y, z] = grid_cell(&candidate);
+
+                // Farthest a conflicting point can be
+                let reach = ((radius + self.max_radius) * 0.5 / cell_size).ceil() as i64;
+
+                let mut conflict = false;
+
+                'search: for k in (z - reach).max(0)..(z + reach + 1).min(dimensions[2]) {
+                    for j in (y - reach).max(0)..(y + reach + 1).min(dimensions[1]) {
+                        for i in (x - reach).max(0)..(x + reach + 1).min(dimensions[0]) {
+                            if let Some(other) = grid[grid_index([i, j, k])] {
+                                let distance = (points[other] - candidate).norm();
+
+                                if distance < (radii[other] + radius) * 0.5 {
+                                    conflict = true;
+                                    break 'search;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                if conflict || conflicts_fixed(&candidate, radius) {
+                    continue;
+                }
+
+                grid[grid_index([x, y, z])] = Some(points.len());
+                active.push(points.len());
+                points.push(candidate);
+                radii.push(radius);
+
+                found = true;
+                break;
+            }
+
+            if !found {
+                active.swap_remove(active_index);
+            }
+        }
+
+        points
+    }
+}
+
+/// Random offset between one and two radius long.
+fn random_offset(rng: &mut Xoshiro256StarStar, radius: f64) -> Vector3<f64> {
+    // Rejection sampling keeps the directions uniform
+    let direction = loop {
+        let direction: Vector3<f64> = Vector3::new(
+            rng.gen_range(-1.0, 1.0),
+            rng.gen_range(-1.0, 1.0),
+            rng.gen_range(-1.0, 1.0),
+        );
+
+        let length = direction.norm_squared();
+
+        if length > 1e-6 && length <= 1.0 {
+            break direction / length.sqrt();
+        }
+    };
+
+    direction * (radius * rng.gen_range(1.0, 2.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variable_radius() {
+        let sampler = PoissonDisk::new(0.05, 0.2).unwrap();
+
+        // Denser toward x = 1
+        let density = |point: &Point3<f64>| point.x;
+
+        let points = sampler.sample(Point3::origin(), Point3::new(1.0, 1.0, 1.0), 3, density);
+
+        assert_eq!(
+            points,
+            sampler.sample(Point3::origin(), Point3::new(1.0, 1.0, 1.0), 3, density)
+        );
+
+        for (index, point) in points.iter().enumerate() {
+            for other in points.iter().skip(index + 1) {
+                let radius =
+                    (sampler.radius(density(point)) + sampler.radius(density(other))) * 0.5;
+
+                assert!((point - other).norm() >= radius);
+            }
+        }
+
+        let sparse = points.iter().filter(|point| point.x < 0.5).count();
+
+        assert!(sparse * 2 < points.len());
+    }
+
+    #[test]
+    fn fixed_points() {
+        let sampler = PoissonDisk::new(0.1, 0.1).unwrap();
+        let density = |_: &Point3<f64>| 1.0;
+
+        // A layer of points just past the x = 1 face
+        let mut fixed = Vec::new();
+
+        for y in 0..10 {
+            for z in 0..10 {
+                fixed.push(Point3::new(
+                    1.05,
+                    y as f64 * 0.1 + 0.05,
+                    z as f64 * 0.1 + 0.05,
+                ));
+            }
+        }
+
+        let points = sampler.sample_with_fixed(
+            Point3::origin(),
+            Point3::new(1.0, 1.0, 1.0),
+            4,
+            density,
+            &fixed,
+        );
+
+        assert!(!points.is_empty());
+        assert!(points
+            .iter()
+            .all(|point| fixed.iter().all(|other| (point - other).norm() >= 0.1)));
+
+        // Without fixed points it is the plain sampling
+        assert_eq!(
+            sampler.sample_with_fixed(
+                Point3::origin(),
+                Point3::new(1.0, 1.0, 1.0),
+                4,
+                density,
+                &[]
+            ),
+            sampler.sample(Point3::origin(), Point3::new(1.0, 1.0, 1.0), 4, density)
+        );
+    }
+
+    #[test]
+    fn invalid_inputs() {
+        assert_eq!(
+            PoissonDisk::new(0.0, 0.2).err(),
+            Some(PoissonError::InvalidRadius)
+        );
+        assert_eq!(
+            PoissonDisk::new(-1.0, 0.2).err(),
+            Some(PoissonError::InvalidRadius)
+        );
+        assert_eq!(
+            PoissonDisk::new(f64::NAN, 0.2).err(),
+            Some(PoissonError::InvalidRadius)
+        );
+        assert_eq!(
+            PoissonDisk::new(0.1, f64::INFINITY).err(),
+            Some(PoissonError::InvalidRadius)
+        );
+
+        let sampler = PoissonDisk::new(0.1, 0.2).unwrap();
+        let density = |_: &Point3<f64>| 1.0;
+
+        let flat = sampler.sample(Point3::origin(), Point3::new(1.0, 0.0, 1.0), 1, density);
+        let inverted = sampler.sample(Point3::new(1.0, 1.0, 1.0), Point3::origin(), 1, density);
+        let endless = sampler.sample(
+            Point3::origin(),
+            Point3::new(f64::NAN, 1.0, 1.0),
+            1,
+            density,
+        );
+
+        assert!(flat.is_empty() && inverted.is_empty() && endless.is_empty());
+
+        // Broken densities fall back to the sparsest spacing instead of looping forever
+        for &broken in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(sampler.radius(broken), sampler.max_radius());
+
+            let points = sampler.sample(
+                Point3::origin(),
+                Point3::new(1.0, 1.0, 1.0),
+                1,
+                |_: &Point3<f64>| broken,
+            );
+
+            assert!(!points.is_empty());
+
+            for (index, point) in points.iter().enumerate() {
+                for other in points.iter().skip(index + 1) {
+                    assert!((point - other).norm() >= sampler.max_radius());
+                }
+            }
+        }
+    }
+}